@@ -0,0 +1,145 @@
+//! Edits the control points of a spline, which have no Transform of their own.
+//!
+//! The Gizmo only transforms Entities, so every control point gets a proxy Entity with a Transform
+//! and a small mesh to click on. A `GizmoTarget` adapter copies the control point into the
+//! Transform of its proxy, and writes every position the Gizmo proposes back into the spline.
+
+use bevy::{ecs::system::SystemParamItem, prelude::*, window::PresentMode};
+use bevy_transform_gizmo::{
+    options::{GizmoModes, GizmoOptions},
+    target::{GizmoTarget, GizmoTargetPlugin},
+    GizmoPickSource, TransformGizmoPlugin,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    present_mode: PresentMode::Immediate,
+                    ..default()
+                }),
+                ..default()
+            }),
+            TransformGizmoPlugin::default(),
+            GizmoTargetPlugin::<ControlPointTarget>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, draw_splines)
+        .run();
+}
+
+/// Control points of a Catmull-Rom spline, stored without any Transform.
+#[derive(Component)]
+struct Spline {
+    points: Vec<Vec3>,
+}
+
+/// Proxy of a control point of a [`Spline`], which the Gizmo transforms instead of the point.
+#[derive(Component)]
+struct ControlPoint {
+    spline: Entity,
+    index: usize,
+}
+
+/// Reads and writes the control point of a proxy Entity. The proxy only follows the position,
+/// its rotation and scale are left as they are.
+struct ControlPointTarget;
+
+impl GizmoTarget for ControlPointTarget {
+    type Param = (
+        Query<'static, 'static, &'static ControlPoint>,
+        Query<'static, 'static, &'static mut Spline>,
+    );
+
+    fn read(
+        entity: Entity,
+        current: &Transform,
+        (q_control_points, q_splines): &mut SystemParamItem<Self::Param>,
+    ) -> Option<Transform> {
+        let control_point = q_control_points.get(entity).ok()?;
+        let spline = q_splines.get(control_point.spline).ok()?;
+        let point = spline.points.get(control_point.index)?;
+        Some(current.with_translation(*point))
+    }
+
+    fn write(
+        entity: Entity,
+        pose: &Transform,
+        (q_control_points, q_splines): &mut SystemParamItem<Self::Param>,
+    ) {
+        let Ok(control_point) = q_control_points.get(entity) else {
+            return;
+        };
+        if let Some(point) = q_splines
+            .get_mut(control_point.spline)
+            .ok()
+            .and_then(|spline| spline.into_inner().points.get_mut(control_point.index))
+        {
+            *point = pose.translation;
+        }
+    }
+}
+
+/// set up a spline with a proxy Entity per control point
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // plane
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(10.0, 10.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.5, 0.3))),
+    ));
+
+    // spline
+    let points = vec![
+        Vec3::new(-3.0, 0.5, 1.0),
+        Vec3::new(-1.0, 1.5, -1.0),
+        Vec3::new(1.0, 0.5, 1.0),
+        Vec3::new(3.0, 1.5, -1.0),
+    ];
+    let proxy_mesh = meshes.add(Sphere::new(0.15));
+    let proxy_material = materials.add(Color::srgb(0.8, 0.8, 0.8));
+    let spline = commands.spawn_empty().id();
+    for (index, point) in points.iter().enumerate() {
+        commands.spawn((
+            Mesh3d(proxy_mesh.clone()),
+            MeshMaterial3d(proxy_material.clone()),
+            Transform::from_translation(*point),
+            ControlPoint { spline, index },
+            // Control points only have a position
+            GizmoOptions {
+                modes: Some(GizmoModes {
+                    translate: true,
+                    rotate: false,
+                    scale: false,
+                }),
+                ..default()
+            },
+        ));
+    }
+    commands.entity(spline).insert(Spline { points });
+
+    // light
+    commands.spawn((PointLight::default(), Transform::from_xyz(4.0, 8.0, 4.0)));
+    // camera
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 5.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
+        GizmoPickSource,
+    ));
+}
+
+/// draw the splines through their control points
+fn draw_splines(mut gizmos: Gizmos, q_splines: Query<&Spline>) {
+    for spline in &q_splines {
+        let Ok(curve) =
+            CubicCardinalSpline::new_catmull_rom(spline.points.iter().copied()).to_curve()
+        else {
+            continue;
+        };
+        gizmos.linestrip(curve.iter_positions(64), Color::srgb(1.0, 0.8, 0.2));
+    }
+}
@@ -0,0 +1,214 @@
+use bevy::{math::bounding::Aabb3d, prelude::*, render::primitives::Aabb};
+
+use crate::snapping::transformed_bounds;
+use crate::*;
+
+/// Limits how the Gizmo can change the Transform of the Entity it is inserted on. Drags and modal
+/// operations enforce it, and the handles it disallows are hidden while the Entity is selected.
+///
+/// Like the Gizmo handles, locked axes are the local axes of the Entity. Ranges and the region are
+/// in the space of its parent.
+#[derive(Component, Clone, Debug, Default)]
+pub struct GizmoConstraints {
+    /// Local axes the Entity can't be moved along.
+    pub lock_translation: BVec3,
+    /// Local axes the Entity can't be rotated around.
+    pub lock_rotation: BVec3,
+    /// Local axes the Entity can't be scaled along.
+    pub lock_scale: BVec3,
+    /// Minimum and maximum of [`Transform::translation`].
+    pub translation_range: Option<(Vec3, Vec3)>,
+    /// Minimum and maximum of [`Transform::scale`].
+    pub scale_range: Option<(Vec3, Vec3)>,
+    /// Region the bounds of the Entity have to stay inside, its origin if it has no [`Aabb`].
+    pub region: Option<Aabb3d>,
+}
+
+impl GizmoConstraints {
+    /// Removes the locked parts from a world space translation and rotation applied on top of
+    /// `start`, the local Transform of the Entity relative to `parent`.
+    pub fn constrain(
+        &self,
+        parent: &GlobalTransform,
+        start: &Transform,
+        translation: Vec3,
+        rotation: Quat,
+    ) -> (Vec3, Quat) {
+        // The locked axes are those of the Entity in world space
+        let orientation = parent.rotation() * start.rotation;
+        let local_translation = orientation.inverse() * translation;
+        let translation =
+            orientation * Vec3::select(self.lock_translation, Vec3::ZERO, local_translation);
+
+        let local_rotation = (orientation.inverse() * rotation * orientation).to_scaled_axis();
+        let local_rotation = Vec3::select(self.lock_rotation, Vec3::ZERO, local_rotation);
+        let rotation = orientation * Quat::from_scaled_axis(local_rotation) * orientation.inverse();
+
+        (translation, rotation)
+    }
+
+    /// Removes the locked axes from a factor applied to the local scale.
+    pub fn constrain_scale(&self, scale: Vec3) -> Vec3 {
+        Vec3::select(self.lock_scale, Vec3::ONE, scale)
+    }
+
+    /// Clamps `transform` into the ranges and the region. Returns the offset it was moved by.
+    pub fn clamp(&self, transform: &mut Transform, aabb: Option<&Aabb>) -> Vec3 {
+        let start = transform.translation;
+        if let Some((min, max)) = self.scale_range {
+            transform.scale = transform.scale.clamp(min, max);
+        }
+        if let Some((min, max)) = self.translation_range {
+            transform.translation = transform.translation.clamp(min, max);
+        }
+        if let Some(region) = self.region {
+            let [bounds_min, _, bounds_max] = transformed_bounds(&transform.compute_affine(), aabb);
+            let (region_min, region_max) = (Vec3::from(region.min), Vec3::from(region.max));
+            let push_inside = (region_min - bounds_min).max(Vec3::ZERO)
+                + (region_max - bounds_max).min(Vec3::ZERO);
+            // Center the bounds on axes the region is too small for
+            let center = (region_min + region_max - bounds_min - bounds_max) / 2.;
+            let too_large = (bounds_max - bounds_min).cmpgt(region_max - region_min);
+            transform.translation += Vec3::select(too_large, center, push_inside);
+        }
+        transform.translation - start
+    }
+
+    /// Returns whether a handle of the Gizmo may be used, with its axis given in the local space
+    /// of the Entity.
+    pub fn allows(&self, handle: GizmoHandle) -> bool {
+        let axis_index = |axis: Vec3| {
+            let axis = axis.abs();
+            if axis.x >= axis.y && axis.x >= axis.z {
+                0
+            } else if axis.y >= axis.z {
+                1
+            } else {
+                2
+            }
+        };
+        let translation_locks = <[bool; 3]>::from(self.lock_translation);
+        match handle {
+            GizmoHandle::Axis(axis) => !translation_locks[axis_index(axis)],
+            GizmoHandle::Plane(normal) => {
+                let normal_index = axis_index(normal);
+                (0..3)
+                    .filter(|index| *index != normal_index)
+                    .all(|index| !translation_locks[index])
+            }
+            GizmoHandle::ViewPlane => !self.lock_translation.all(),
+            GizmoHandle::Ring(axis) => !<[bool; 3]>::from(self.lock_rotation)[axis_index(axis)],
+            GizmoHandle::Scale(axis) => !<[bool; 3]>::from(self.lock_scale)[axis_index(axis)],
+        }
+    }
+}
+
+/// Clamps the selection with its [`GizmoConstraints`] after a drag or modal operation changed it,
+/// moving the Gizmo root along so it stays on the pivot. The selection comes with the Global
+/// Transform of its parent.
+pub fn clamp_selection(
+    q_local_transform: &mut Query<&mut Transform>,
+    q_constraints: &Query<(&GizmoConstraints, Option<&Aabb>)>,
+    gizmo: Entity,
+    selection: Option<(Entity, &GlobalTransform)>,
+) {
+    let Some((sel_entity, parent, (constraints, aabb))) = selection.and_then(|(entity, parent)| {
+        q_constraints
+            .get(entity)
+            .ok()
+            .map(|found| (entity, parent, found))
+    }) else {
+        return;
+    };
+    let Ok(mut sel_transform) = q_local_transform.get_mut(sel_entity) else {
+        return;
+    };
+    // The correction is in the space of the parent
+    let correction = parent
+        .affine()
+        .transform_vector3(constraints.clamp(&mut sel_transform, aabb));
+    if let Ok(mut gizmo_transform) = q_local_transform.get_mut(gizmo) {
+        gizmo_transform.translation += correction;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constrain_removes_locked_local_axes() {
+        let constraints = GizmoConstraints {
+            lock_translation: BVec3::new(true, false, false),
+            lock_rotation: BVec3::new(false, true, false),
+            ..default()
+        };
+        // Local X points along world Z
+        let start = Transform::from_rotation(Quat::from_rotation_y(-90f32.to_radians()));
+        let (translation, rotation) = constraints.constrain(
+            &GlobalTransform::IDENTITY,
+            &start,
+            Vec3::new(1., 2., 3.),
+            Quat::from_rotation_y(1.),
+        );
+        assert!(translation.abs_diff_eq(Vec3::new(1., 2., 0.), 1e-5));
+        assert!(rotation.abs_diff_eq(Quat::IDENTITY, 1e-5));
+    }
+
+    #[test]
+    fn constrain_locks_axes_of_parented_entities() {
+        let constraints = GizmoConstraints {
+            lock_translation: BVec3::new(true, false, false),
+            ..default()
+        };
+        // Local X of the Entity points along world -Z through its parent
+        let parent = GlobalTransform::from(Transform::from_rotation(Quat::from_rotation_y(
+            90f32.to_radians(),
+        )));
+        let (translation, _) = constraints.constrain(
+            &parent,
+            &Transform::IDENTITY,
+            Vec3::new(1., 2., 3.),
+            Quat::IDENTITY,
+        );
+        assert!(translation.abs_diff_eq(Vec3::new(1., 2., 0.), 1e-5));
+    }
+
+    #[test]
+    fn constrain_scale_keeps_locked_axes() {
+        let constraints = GizmoConstraints {
+            lock_scale: BVec3::new(false, true, false),
+            ..default()
+        };
+        assert_eq!(
+            constraints.constrain_scale(Vec3::splat(2.)),
+            Vec3::new(2., 1., 2.)
+        );
+    }
+
+    #[test]
+    fn clamp_into_ranges() {
+        let constraints = GizmoConstraints {
+            translation_range: Some((Vec3::ZERO, Vec3::ONE)),
+            scale_range: Some((Vec3::splat(0.5), Vec3::splat(2.))),
+            ..default()
+        };
+        let mut transform = Transform::from_xyz(2., 0.5, -1.).with_scale(Vec3::splat(4.));
+        let offset = constraints.clamp(&mut transform, None);
+        assert_eq!(transform.translation, Vec3::new(1., 0.5, 0.));
+        assert_eq!(transform.scale, Vec3::splat(2.));
+        assert_eq!(offset, Vec3::new(-1., 0., 1.));
+    }
+
+    #[test]
+    fn clamp_bounds_into_region() {
+        let constraints = GizmoConstraints {
+            region: Some(Aabb3d::new(Vec3::ZERO, Vec3::splat(5.))),
+            ..default()
+        };
+        let aabb = Aabb::from_min_max(Vec3::splat(-1.), Vec3::splat(1.));
+        let mut transform = Transform::from_xyz(4.5, 0., -10.);
+        constraints.clamp(&mut transform, Some(&aabb));
+        assert_eq!(transform.translation, Vec3::new(4., 0., -4.));
+    }
+}
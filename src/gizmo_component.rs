@@ -1,17 +1,394 @@
 use bevy::{
-    core_pipeline::core_3d::Camera3dDepthLoadOp, pbr::NotShadowCaster, prelude::*,
+    core_pipeline::{core_3d::Camera3dDepthLoadOp, tonemapping::Tonemapping},
+    pbr::NotShadowCaster,
+    prelude::*,
     render::view::RenderLayers,
 };
 
 use crate::normalization::*;
 use crate::*;
 
-/// Startup system that builds the procedural mesh and materials of the gizmo.
+/// A draggable part of the Gizmo, interpreted by [`drag_handle`]. Axes are in the local space of
+/// the Gizmo root.
+///
+/// Spawn a [`GizmoHandleBundle`] to add a custom handle.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+#[require(TransformGizmoPart)]
+pub enum GizmoHandle {
+    /// Moves along an axis.
+    Axis(Vec3),
+    /// Moves on the plane with the given normal.
+    Plane(Vec3),
+    /// Moves on the view plane of the camera.
+    ViewPlane,
+    /// Rotates around an axis.
+    Ring(Vec3),
+    /// Scales along an axis.
+    Scale(Vec3),
+}
+
+impl GizmoHandle {
+    /// Returns the handle with its axis rotated into another space.
+    pub fn rotated(self, rotation: Quat) -> Self {
+        match self {
+            Self::Axis(axis) => Self::Axis(rotation * axis),
+            Self::Plane(normal) => Self::Plane(rotation * normal),
+            Self::ViewPlane => Self::ViewPlane,
+            Self::Ring(axis) => Self::Ring(rotation * axis),
+            Self::Scale(axis) => Self::Scale(rotation * axis),
+        }
+    }
+
+    /// Returns two axes spanning the plane with the given normal, the other two coordinate axes
+    /// in cyclic order if the normal is one of them.
+    pub fn plane_axes(normal: Vec3) -> (Vec3, Vec3) {
+        let normal = normal.normalize_or(Vec3::Y);
+        match [Vec3::X, Vec3::Y, Vec3::Z]
+            .into_iter()
+            .position(|axis| axis.dot(normal).abs() > 0.999)
+        {
+            Some(0) => (Vec3::Y, Vec3::Z),
+            Some(1) => (Vec3::Z, Vec3::X),
+            Some(_) => (Vec3::X, Vec3::Y),
+            None => normal.any_orthonormal_pair(),
+        }
+    }
+}
+
+/// Handles spawned by [`build_gizmo`].
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GizmoLayout {
+    /// Arrows that move along an axis.
+    pub axes: bool,
+    /// Squares that move on a plane.
+    pub planes: bool,
+    /// Center sphere that moves on the view plane.
+    pub view_plane: bool,
+    /// Arcs that rotate around an axis.
+    pub rings: bool,
+    /// Cubes that scale along an axis.
+    pub scale: bool,
+}
+
+impl Default for GizmoLayout {
+    fn default() -> Self {
+        Self {
+            axes: true,
+            planes: true,
+            view_plane: true,
+            rings: true,
+            scale: false,
+        }
+    }
+}
+
+/// How the Gizmo is drawn relative to the scene.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoRenderMode {
+    /// Always on top, rendered by the [`InternalGizmoCamera`].
+    #[default]
+    Overlay,
+    /// In the main pass of the [`GizmoPickSource`] camera, occluded by the scene.
+    DepthTested,
+    /// In the main pass of the [`GizmoPickSource`] camera, with the occluded parts drawn faded.
+    Hybrid,
+}
+
+/// Rendering of the Gizmo. In [`GizmoRenderMode::Overlay`] it is drawn on top of the scene by the
+/// [`InternalGizmoCamera`], which otherwise copies the settings of the main camera. Changing the
+/// render mode or layer rebuilds the Gizmo, cancelling the drag in progress, the other settings
+/// are applied to the overlay camera directly.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct GizmoOverlay {
+    pub render_mode: GizmoRenderMode,
+    /// Render layer of the Gizmo parts, the drag guides and the overlay camera. Pick one the app
+    /// doesn't use. The other render modes use the render layers of the main camera.
+    pub render_layer: usize,
+    /// Added to the order of the main camera, the overlay has to render after it.
+    pub order_offset: isize,
+    /// Overrides the Msaa of the main camera.
+    pub msaa: Option<Msaa>,
+    /// Overrides the tonemapping of the main camera.
+    pub tonemapping: Option<Tonemapping>,
+}
+
+/// The [`GizmoOverlay`] settings the [`TransformGizmo`] was built with. It is rebuilt once they
+/// differ from the resource.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct GizmoBuiltOverlay {
+    pub render_mode: GizmoRenderMode,
+    pub render_layer: usize,
+}
+
+impl From<&GizmoOverlay> for GizmoBuiltOverlay {
+    fn from(overlay: &GizmoOverlay) -> Self {
+        Self {
+            render_mode: overlay.render_mode,
+            render_layer: overlay.render_layer,
+        }
+    }
+}
+
+/// Marks the main camera the Gizmo added a [`MeshPickingCamera`] to in the render modes without an
+/// overlay camera. Both are removed again with the Gizmo.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct GizmoMeshPickingCamera;
+
+impl GizmoOverlay {
+    pub fn render_layers(&self) -> RenderLayers {
+        RenderLayers::layer(self.render_layer)
+    }
+
+    /// Returns the render layers of the Gizmo parts, given those of the main camera.
+    pub fn part_layers(&self, camera_layers: Option<&RenderLayers>) -> RenderLayers {
+        match self.render_mode {
+            GizmoRenderMode::Overlay => self.render_layers(),
+            GizmoRenderMode::DepthTested | GizmoRenderMode::Hybrid => {
+                camera_layers.cloned().unwrap_or_default()
+            }
+        }
+    }
+}
+
+impl Default for GizmoOverlay {
+    fn default() -> Self {
+        Self {
+            render_mode: GizmoRenderMode::default(),
+            render_layer: 12,
+            order_offset: 10,
+            msaa: None,
+            tonemapping: None,
+        }
+    }
+}
+
+/// Spawns a Gizmo instance for this Entity, which stays on it independently of the selection of
+/// the [`TransformGizmo`]. Removing the component despawns the instance. Several Entities can
+/// carry one at the same time, each following its own [`GizmoOptions`].
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct TransformGizmoTarget;
+
+/// Root of a Gizmo instance spawned for an Entity with a [`TransformGizmoTarget`].
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransformGizmoInstance {
+    /// The Entity the instance transforms.
+    pub target: Entity,
+}
+
+/// Added to a [`TransformGizmoTarget`] once its Gizmo instance exists, naming the instance root.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TargetGizmoInstance(pub Entity);
+
+impl TransformGizmoResource {
+    /// Returns the Entity transformed by the Gizmo root `gizmo`, the target of an instance or the
+    /// selection of the [`TransformGizmo`].
+    pub fn selection_of(
+        &self,
+        gizmo: Entity,
+        q_instances: &Query<&TransformGizmoInstance>,
+    ) -> Option<Entity> {
+        match q_instances.get(gizmo) {
+            Ok(instance) => Some(instance.target),
+            Err(_) => self.entity,
+        }
+    }
+}
+
+/// Spawns a Gizmo instance for every [`TransformGizmoTarget`] that has none yet.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn spawn_target_gizmos(
+    mut commands: Commands,
+    layout: Res<GizmoLayout>,
+    overlay: Res<GizmoOverlay>,
+    q_camera: Query<Option<&RenderLayers>, With<GizmoPickSource>>,
+    q_targets: Query<Entity, (With<TransformGizmoTarget>, Without<TargetGizmoInstance>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GizmoMaterial>>,
+) {
+    for target in &q_targets {
+        let root = spawn_gizmo(
+            &mut commands,
+            &layout,
+            &overlay,
+            overlay.part_layers(q_camera.single().ok().flatten()),
+            &mut meshes,
+            &mut materials,
+        );
+        commands
+            .entity(root)
+            .insert(TransformGizmoInstance { target });
+        commands.entity(target).insert(TargetGizmoInstance(root));
+    }
+}
+
+/// This Observer Function despawns the Gizmo instance of an Entity that lost its
+/// [`TransformGizmoTarget`] or was despawned, keeping the user-defined handles attached to it
+pub fn despawn_target_gizmo(
+    trigger: Trigger<OnRemove, TransformGizmoTarget>,
+    mut commands: Commands,
+    q_targets: Query<&TargetGizmoInstance>,
+    q_attached_handles: Query<(Entity, &ChildOf), With<AttachedGizmoHandle>>,
+) {
+    let Ok(instance) = q_targets.get(trigger.target()) else {
+        return;
+    };
+    detach_gizmo_handles(&mut commands, &q_attached_handles, |root| {
+        root == instance.0
+    });
+    commands.entity(instance.0).try_despawn();
+    commands
+        .entity(trigger.target())
+        .try_remove::<TargetGizmoInstance>();
+}
+
+/// Builds the procedural mesh and materials of the gizmo, and its overlay camera in
+/// [`GizmoRenderMode::Overlay`]. Runs once the Gizmo is [`GizmoEnabled`] and doesn't exist yet.
+#[allow(clippy::type_complexity)]
 pub fn build_gizmo(
     mut commands: Commands,
+    layout: Res<GizmoLayout>,
+    overlay: Res<GizmoOverlay>,
+    q_camera: Query<(Entity, Option<&RenderLayers>, Has<MeshPickingCamera>), With<GizmoPickSource>>,
+    mut config_store: ResMut<GizmoConfigStore>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<GizmoMaterial>>,
 ) {
+    let main_camera = q_camera.single().ok();
+    let render_layers = overlay.part_layers(main_camera.and_then(|(_, layers, _)| layers));
+    let parent = spawn_gizmo(
+        &mut commands,
+        &layout,
+        &overlay,
+        render_layers.clone(),
+        &mut meshes,
+        &mut materials,
+    );
+    commands
+        .entity(parent)
+        .insert((TransformGizmo, GizmoBuiltOverlay::from(&*overlay)));
+
+    let (lines_config, _) = config_store.config_mut::<TransformGizmoLines>();
+    lines_config.render_layers = render_layers.clone();
+    // Drag guides stay visible through the scene unless everything is depth tested
+    lines_config.depth_bias = match overlay.render_mode {
+        GizmoRenderMode::Hybrid => -1.,
+        GizmoRenderMode::Overlay | GizmoRenderMode::DepthTested => 0.,
+    };
+
+    // Swept angle of rotation drags, placed by `update_swept_angle_pie`
+    commands.spawn((
+        Mesh3d(meshes.add(pie::Pie::default())),
+        MeshMaterial3d(materials.add(GizmoMaterial::from(Color::WHITE.with_alpha(0.3)))),
+        Visibility::Hidden,
+        NotShadowCaster,
+        Pickable::IGNORE,
+        SweptAnglePie,
+        render_layers,
+    ));
+
+    if overlay.render_mode != GizmoRenderMode::Overlay {
+        // The main camera picks the Gizmo, even if mesh picking requires markers
+        if let Some((camera_entity, _, false)) = main_camera {
+            commands
+                .entity(camera_entity)
+                .insert((MeshPickingCamera, GizmoMeshPickingCamera));
+        }
+        return;
+    }
+    commands.spawn((
+        Camera3d {
+            depth_load_op: Camera3dDepthLoadOp::Clear(0.),
+            ..default()
+        },
+        Camera {
+            clear_color: ClearColorConfig::None,
+            ..default()
+        },
+        InternalGizmoCamera,
+        overlay.render_layers(),
+    ));
+}
+
+/// Despawns the Gizmo, its instances and its overlay camera once it is no longer [`GizmoEnabled`] or
+/// its render mode or layer changed, see [`gizmo_outdated`], cancelling the drag or modal operation in
+/// progress. User-defined handles attached to the Gizmo are kept, detached and hidden until it is
+/// rebuilt.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn despawn_gizmo(
+    mut commands: Commands,
+    mut drag_state: ResMut<GizmoDrag>,
+    mut modal: ResMut<GizmoModal>,
+    mut q_local_transform: Query<&mut Transform>,
+    q_attached_handles: Query<(Entity, &ChildOf), With<AttachedGizmoHandle>>,
+    q_instances: Query<&TransformGizmoInstance>,
+    q_picking_cameras: Query<Entity, With<GizmoMeshPickingCamera>>,
+    #[cfg(feature = "readout")] mut q_readout: Query<&mut Visibility, With<readout::GizmoReadout>>,
+    q_gizmo_entities: Query<
+        Entity,
+        Or<(
+            With<TransformGizmo>,
+            With<TransformGizmoInstance>,
+            With<SweptAnglePie>,
+            With<InternalGizmoCamera>,
+        )>,
+    >,
+) {
+    if let Some(active) = drag_state.active.take() {
+        active.start.restore(&mut q_local_transform);
+    }
+    if let Some(active) = modal.active.take() {
+        active.start.restore(&mut q_local_transform);
+    }
+    // The readout isn't updated while disabled, hide what it showed of the cancelled operation
+    #[cfg(feature = "readout")]
+    for mut visibility in &mut q_readout {
+        visibility.set_if_neq(Visibility::Hidden);
+    }
+    detach_gizmo_handles(&mut commands, &q_attached_handles, |root| {
+        q_gizmo_entities.contains(root)
+    });
+    // The targets get new instances once the Gizmo is rebuilt
+    for instance in &q_instances {
+        commands
+            .entity(instance.target)
+            .try_remove::<TargetGizmoInstance>();
+    }
+    for camera in &q_picking_cameras {
+        commands
+            .entity(camera)
+            .remove::<(MeshPickingCamera, GizmoMeshPickingCamera)>();
+    }
+    for entity in &q_gizmo_entities {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Detaches and hides the user-defined handles attached to the Gizmo roots `is_root` accepts, so
+/// they aren't despawned with them. [`attach_gizmo_handles`] attaches them again.
+fn detach_gizmo_handles(
+    commands: &mut Commands,
+    q_attached_handles: &Query<(Entity, &ChildOf), With<AttachedGizmoHandle>>,
+    is_root: impl Fn(Entity) -> bool,
+) {
+    for (entity, parent) in q_attached_handles {
+        if is_root(parent.parent()) {
+            commands
+                .entity(entity)
+                .remove::<(ChildOf, RenderLayers, AttachedGizmoHandle)>()
+                .insert(Visibility::Hidden);
+        }
+    }
+}
+
+/// Spawns a Gizmo root with the handles of the `layout`, and returns the root.
+fn spawn_gizmo(
+    commands: &mut Commands,
+    layout: &GizmoLayout,
+    overlay: &GizmoOverlay,
+    render_layers: RenderLayers,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<GizmoMaterial>,
+) -> Entity {
     let axis_length = 1.3;
     let arc_radius = 1.;
     let plane_size = axis_length * 0.25;
@@ -33,15 +410,9 @@ pub fn build_gizmo(
         ring_radius: 0.04,
         ..Default::default()
     }));
-    //let cube_mesh = meshes.add(Mesh::from(shape::Cube { size: 0.15 })).id();
+    let cube_mesh = meshes.add(Cuboid::from_length(0.15));
     // Define gizmo materials
     let (s, l) = (0.8, 0.6);
-    let gizmo_matl_x = materials.add(GizmoMaterial::from(Color::hsl(0.0, s, l)));
-    let gizmo_matl_y = materials.add(GizmoMaterial::from(Color::hsl(120.0, s, l)));
-    let gizmo_matl_z = materials.add(GizmoMaterial::from(Color::hsl(240.0, s, l)));
-    let gizmo_matl_x_sel = materials.add(GizmoMaterial::from(Color::hsl(0.0, s, l)));
-    let gizmo_matl_y_sel = materials.add(GizmoMaterial::from(Color::hsl(120.0, s, l)));
-    let gizmo_matl_z_sel = materials.add(GizmoMaterial::from(Color::hsl(240.0, s, l)));
     let gizmo_matl_v_sel = materials.add(GizmoMaterial::from(Color::hsl(0., 0.0, l)));
 
     // Build the gizmo using the variables above.
@@ -49,8 +420,7 @@ pub fn build_gizmo(
         .spawn((
             Transform::from_xyz(0.0, 0.0, 0.0),
             Visibility::default(),
-            RenderLayers::layer(12),
-            TransformGizmo,
+            render_layers.clone(),
             Normalize3d {
                 size_in_world: 1.5,
                 desired_pixel_size: 150.0,
@@ -58,241 +428,110 @@ pub fn build_gizmo(
         ))
         .id();
 
-    // Translation Axes
-    let translation_x_axis = commands
-        .spawn((
-            Mesh3d(arrow_tail_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_x.clone()),
-            Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_rotation_z(std::f32::consts::PI / 2.0),
-                Vec3::new(axis_length / 2.0, 0.0, 0.0),
-            )),
-            RenderLayers::layer(12),
-            NotShadowCaster,
-            TransformGizmoPart,
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[translation_x_axis]);
-    commands.entity(translation_x_axis).observe(transform_axis);
-
-    let translation_y_axis = commands
-        .spawn((
-            Mesh3d(arrow_tail_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_y.clone()),
-            Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_rotation_y(std::f32::consts::PI / 2.0),
-                Vec3::new(0.0, axis_length / 2.0, 0.0),
-            )),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[translation_y_axis]);
-    commands.entity(translation_y_axis).observe(transform_axis);
-
-    let translation_z_axis = commands
-        .spawn((
-            Mesh3d(arrow_tail_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_z.clone()),
-            Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_rotation_x(std::f32::consts::PI / 2.0),
-                Vec3::new(0.0, 0.0, axis_length / 2.0),
-            )),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[translation_z_axis]);
-    commands.entity(translation_z_axis).observe(transform_axis);
+    let mut handles: Vec<(GizmoHandle, Handle<Mesh>, Handle<GizmoMaterial>, Transform)> =
+        Vec::new();
+    // Rotations that orient the open side of each arc
+    let arc_rotations = [
+        Quat::from_axis_angle(Vec3::Z, f32::to_radians(90.0)),
+        Quat::IDENTITY,
+        Quat::from_axis_angle(Vec3::Z, f32::to_radians(90.0))
+            * Quat::from_axis_angle(Vec3::X, f32::to_radians(90.0)),
+    ];
+    for ((axis, hue), arc_rotation) in [(Vec3::X, 0.0), (Vec3::Y, 120.0), (Vec3::Z, 240.0)]
+        .into_iter()
+        .zip(arc_rotations)
+    {
+        let gizmo_matl = materials.add(GizmoMaterial::from(Color::hsl(hue, s, l)));
+        let gizmo_matl_sel = materials.add(GizmoMaterial::from(Color::hsl(hue, s, l)));
+        // Meshes are built along +Y
+        let along_axis = Quat::from_rotation_arc(Vec3::Y, axis);
 
-    // Translation Handles
-    let translation_x_handle = commands
-        .spawn((
-            Mesh3d(cone_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_x_sel.clone()),
-            Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_rotation_z(std::f32::consts::PI / -2.0),
-                Vec3::new(axis_length, 0.0, 0.0),
-            )),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands
-        .entity(parent)
-        .add_children(&[translation_x_handle]);
-    commands
-        .entity(translation_x_handle)
-        .observe(transform_axis);
+        if layout.axes {
+            // Translation Axes
+            handles.push((
+                GizmoHandle::Axis(axis),
+                arrow_tail_mesh.clone(),
+                gizmo_matl.clone(),
+                Transform::from_rotation(along_axis).with_translation(axis * axis_length / 2.0),
+            ));
+            // Translation Handles
+            handles.push((
+                GizmoHandle::Axis(axis),
+                cone_mesh.clone(),
+                gizmo_matl_sel.clone(),
+                Transform::from_rotation(along_axis).with_translation(axis * axis_length),
+            ));
+        }
+        if layout.planes {
+            handles.push((
+                GizmoHandle::Plane(axis),
+                plane_mesh.clone(),
+                gizmo_matl_sel.clone(),
+                Transform::from_rotation(along_axis)
+                    .with_translation((Vec3::ONE - axis) * plane_offset),
+            ));
+        }
+        if layout.rings {
+            // Rotation Arcs
+            handles.push((
+                GizmoHandle::Ring(axis),
+                rotation_mesh.clone(),
+                gizmo_matl.clone(),
+                Transform::from_rotation(arc_rotation),
+            ));
+        }
+        if layout.scale {
+            handles.push((
+                GizmoHandle::Scale(axis),
+                cube_mesh.clone(),
+                gizmo_matl_sel.clone(),
+                Transform::from_rotation(along_axis).with_translation(axis * (axis_length + 0.35)),
+            ));
+        }
+    }
+    if layout.view_plane {
+        handles.push((
+            GizmoHandle::ViewPlane,
+            sphere_mesh.clone(),
+            gizmo_matl_v_sel.clone(),
+            Transform::IDENTITY,
+        ));
+    }
 
-    let translation_x_plane = commands
-        .spawn((
-            Mesh3d(plane_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_x_sel.clone()),
-            Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_rotation_z(std::f32::consts::PI / -2.0),
-                Vec3::new(0., plane_offset, plane_offset),
-            )),
+    for (handle, mesh, material, transform) in handles {
+        let part = commands
+            .spawn((
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material.clone()),
+                transform,
+                NotShadowCaster,
+                handle,
+                render_layers.clone(),
+                ChildOf(parent),
+            ))
+            .id();
+        if overlay.render_mode != GizmoRenderMode::Hybrid {
+            continue;
+        }
+        // Faded copy drawn only where the scene occludes the part
+        let color = materials
+            .get(&material)
+            .map_or(LinearRgba::WHITE, |material| material.color);
+        let occluded_material = materials.add(GizmoMaterial {
+            color: color.with_alpha(color.alpha * 0.25),
+            alpha_mode: AlphaMode::Blend,
+            depth_compare: GizmoDepthCompare::Occluded,
+        });
+        commands.spawn((
+            Mesh3d(mesh),
+            MeshMaterial3d(occluded_material),
             NotShadowCaster,
             TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
+            Pickable::IGNORE,
+            render_layers.clone(),
+            ChildOf(part),
+        ));
+    }
 
-    commands.entity(parent).add_children(&[translation_x_plane]);
-    commands
-        .entity(translation_x_plane)
-        .observe(transform_plane);
-
-    let translation_y_handle = commands
-        .spawn((
-            Mesh3d(cone_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_y_sel.clone()),
-            Transform::from_translation(Vec3::new(0.0, axis_length, 0.0)),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands
-        .entity(parent)
-        .add_children(&[translation_y_handle]);
-    commands
-        .entity(translation_y_handle)
-        .observe(transform_axis);
-
-    let translation_y_plane = commands
-        .spawn((
-            Mesh3d(plane_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_y_sel.clone()),
-            Transform::from_translation(Vec3::new(plane_offset, 0.0, plane_offset)),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[translation_y_plane]);
-    commands
-        .entity(translation_y_plane)
-        .observe(transform_plane);
-
-    let translation_z_handle = commands
-        .spawn((
-            Mesh3d(cone_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_z_sel.clone()),
-            Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_rotation_x(std::f32::consts::PI / 2.0),
-                Vec3::new(0.0, 0.0, axis_length),
-            )),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands
-        .entity(parent)
-        .add_children(&[translation_z_handle]);
-    commands
-        .entity(translation_z_handle)
-        .observe(transform_axis);
-
-    let translation_z_plane = commands
-        .spawn((
-            Mesh3d(plane_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_z_sel.clone()),
-            Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_rotation_x(std::f32::consts::PI / 2.0),
-                Vec3::new(plane_offset, plane_offset, 0.0),
-            )),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[translation_z_plane]);
-    commands
-        .entity(translation_z_plane)
-        .observe(transform_plane);
-
-    let handle = commands
-        .spawn((
-            Mesh3d(sphere_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_v_sel.clone()),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[handle]);
-    commands.entity(handle).observe(transform_camera_plane);
-
-    // Rotation Arcs
-    let rotation_x_arc = commands
-        .spawn((
-            Mesh3d(rotation_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_x.clone()),
-            Transform::from_rotation(Quat::from_axis_angle(Vec3::Z, f32::to_radians(90.0))),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[rotation_x_arc]);
-    commands.entity(rotation_x_arc).observe(transform_rotation);
-
-    let rotation_y_arc = commands
-        .spawn((
-            Mesh3d(rotation_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_y.clone()),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[rotation_y_arc]);
-    commands.entity(rotation_y_arc).observe(transform_rotation);
-
-    let rotation_z_arc = commands
-        .spawn((
-            Mesh3d(rotation_mesh.clone()),
-            MeshMaterial3d(gizmo_matl_z.clone()),
-            Transform::from_rotation(
-                Quat::from_axis_angle(Vec3::Z, f32::to_radians(90.0))
-                    * Quat::from_axis_angle(Vec3::X, f32::to_radians(90.0)),
-            ),
-            NotShadowCaster,
-            TransformGizmoPart,
-            RenderLayers::layer(12),
-        ))
-        .id();
-
-    commands.entity(parent).add_children(&[rotation_z_arc]);
-    commands.entity(rotation_z_arc).observe(transform_rotation);
-
-    commands.spawn((
-        Camera3d {
-            depth_load_op: Camera3dDepthLoadOp::Clear(0.),
-            ..default()
-        },
-        Camera {
-            clear_color: ClearColorConfig::None,
-            ..default()
-        },
-        InternalGizmoCamera,
-        RenderLayers::layer(12),
-    ));
+    parent
 }
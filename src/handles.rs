@@ -0,0 +1,104 @@
+use bevy::{pbr::NotShadowCaster, prelude::*, render::view::RenderLayers};
+
+use crate::*;
+
+/// A user-defined Gizmo handle. Spawn it anywhere, it is attached to the [`TransformGizmo`] root,
+/// or to a Gizmo instance with a [`GizmoHandleTarget`], and placed relative to it by its Transform.
+///
+/// Like the built-in parts it is picked on the Gizmo render layer, receives the usual pointer
+/// events (observe `Pointer<Click>` on it for a button) and is hidden by the [`GizmoModes`] and
+/// [`GizmoConstraints`] that disallow its [`GizmoHandle`]. Add a [`GizmoHandleAction`] to map its
+/// drags to a custom action instead of transforming the selection.
+#[derive(Bundle)]
+pub struct GizmoHandleBundle {
+    pub handle: GizmoHandle,
+    pub mesh: Mesh3d,
+    pub material: MeshMaterial3d<GizmoMaterial>,
+    /// Placement relative to the Gizmo root.
+    pub transform: Transform,
+    pub not_shadow_caster: NotShadowCaster,
+}
+
+impl GizmoHandleBundle {
+    pub fn new(
+        handle: GizmoHandle,
+        mesh: Handle<Mesh>,
+        material: Handle<GizmoMaterial>,
+        transform: Transform,
+    ) -> Self {
+        Self {
+            handle,
+            mesh: Mesh3d(mesh),
+            material: MeshMaterial3d(material),
+            transform,
+            not_shadow_caster: NotShadowCaster,
+        }
+    }
+}
+
+/// Makes a [`GizmoHandle`] trigger [`GizmoHandleDrag`] on itself instead of transforming the
+/// selection, e.g. to adjust the length of a pipe.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct GizmoHandleAction;
+
+/// Triggered on a [`GizmoHandle`] with a [`GizmoHandleAction`] for every drag event on it, with the
+/// pointer motion mapped onto the constraint of the handle.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct GizmoHandleDrag {
+    /// The selected Entity, if any.
+    pub selection: Option<Entity>,
+    pub constraint: DragConstraint,
+    /// World space offset along the constraint since the last event.
+    pub translation: Vec3,
+    /// Rotation around the axis of a ring handle since the last event.
+    pub rotation: Quat,
+    /// Factor along the axis of a scale handle since the last event.
+    pub scale: f32,
+}
+
+/// Marks a user-defined [`GizmoHandle`] that [`attach_gizmo_handles`] attached to the Gizmo. It is
+/// detached and hidden instead of despawned with the Gizmo, and attached again once it is rebuilt.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct AttachedGizmoHandle;
+
+/// Attaches a user-defined [`GizmoHandle`] to the Gizmo instance of a [`TransformGizmoTarget`]
+/// Entity instead of the [`TransformGizmo`].
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GizmoHandleTarget(pub Entity);
+
+/// Attaches user-defined [`GizmoHandle`]s that were spawned without a parent, or detached when the
+/// Gizmo was despawned, to the Gizmo root, or to the instance named by their [`GizmoHandleTarget`]
+/// once it exists, and moves them to its render layers.
+#[allow(clippy::type_complexity)]
+pub fn attach_gizmo_handles(
+    mut commands: Commands,
+    q_gizmo: Single<(Entity, &RenderLayers), With<TransformGizmo>>,
+    q_targets: Query<&TargetGizmoInstance>,
+    q_instances: Query<&RenderLayers, With<TransformGizmoInstance>>,
+    q_handles: Query<
+        (Entity, Has<ChildOf>, Option<&GizmoHandleTarget>),
+        (With<GizmoHandle>, Without<RenderLayers>),
+    >,
+) {
+    for (entity, has_parent, target) in &q_handles {
+        let (root, render_layers) = match target {
+            None => *q_gizmo,
+            Some(target) => {
+                let Some(instance) = q_targets.get(target.0).ok().and_then(|instance| {
+                    q_instances
+                        .get(instance.0)
+                        .ok()
+                        .map(|render_layers| (instance.0, render_layers))
+                }) else {
+                    continue;
+                };
+                instance
+            }
+        };
+        let mut handle = commands.entity(entity);
+        handle.insert((render_layers.clone(), AttachedGizmoHandle));
+        if !has_parent {
+            handle.insert(ChildOf(root));
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use bevy::asset::load_internal_asset;
-use bevy::{color::palettes::tailwind::*, prelude::*};
+use bevy::{color::palettes::tailwind::*, core_pipeline::tonemapping::Tonemapping, prelude::*};
 
 pub mod mesh;
 use bevy_log::error;
@@ -12,13 +12,44 @@ pub mod transformations;
 use transformations::*;
 
 pub mod gizmo_component;
-use crate::gizmo_material::GizmoMaterial;
+use crate::gizmo_material::{GizmoDepthCompare, GizmoMaterial};
 use gizmo_component::*;
 
 pub mod normalization;
 use crate::normalization::*;
 
+pub mod modal;
+use modal::*;
+
+pub mod visuals;
+use visuals::*;
+
+pub mod pivot;
+use pivot::*;
+
+pub mod snapping;
+use snapping::*;
+
+pub mod constraints;
+use constraints::*;
+
+pub mod options;
+use options::*;
+
+pub mod validation;
+use validation::*;
+
+pub mod handles;
+use handles::*;
+
+pub mod target;
+use target::GizmoFloatingOrigin;
+
+#[cfg(feature = "readout")]
+pub mod readout;
+
 #[derive(Clone, Component, Debug, Default)]
+#[require(MeshPickingCamera)]
 pub struct InternalGizmoCamera;
 
 #[derive(Component)]
@@ -30,9 +61,96 @@ pub struct GizmoPickSource;
 #[derive(Component)]
 pub struct GizmoTransformable;
 
-#[derive(Component)]
+#[derive(Component, Default)]
+#[require(Pickable)]
 pub struct TransformGizmoPart;
 
+/// Blocks all gizmo input (selection and handle drags) while set to `true`.
+///
+/// Picking already respects blocking entities, so bevy_ui nodes with a blocking [`Pickable`]
+/// keep clicks and drags from reaching the scene behind them. This resource covers input layers
+/// outside of `bevy_picking`, e.g. an egui integration that sets it every frame while the pointer
+/// is over one of its windows.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GizmoInputBlocked(pub bool);
+
+/// Run condition that is `true` while gizmo input isn't blocked by [`GizmoInputBlocked`].
+pub fn gizmo_input_allowed(blocked: Res<GizmoInputBlocked>) -> bool {
+    !blocked.0
+}
+
+/// Whether the Gizmo exists. While `false` the Gizmo, its instances and its overlay camera are
+/// despawned, clicks don't select anything and the systems of the plugin don't run, e.g. in the
+/// play mode of an editor. Setting it back to `true` rebuilds the Gizmo on the current selection.
+///
+/// A [`MeshPickingPlugin`] registered by [`GizmoPickingBackend::Mesh`] keeps ray casting the scene
+/// under the pointer while disabled, as the app may rely on its hits. With
+/// [`GizmoPickingBackend::HandlesOnly`] nothing is ray cast for the Gizmo while it is disabled.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GizmoEnabled(pub bool);
+
+impl Default for GizmoEnabled {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Run condition that is `true` while the Gizmo is [`GizmoEnabled`].
+pub fn gizmo_enabled(enabled: Res<GizmoEnabled>) -> bool {
+    enabled.0
+}
+
+/// Run condition that is `true` while the Gizmo exists but is no longer [`GizmoEnabled`], or was
+/// built with another render mode or layer than the [`GizmoOverlay`] has now, and has to be
+/// despawned.
+pub fn gizmo_outdated(
+    enabled: Res<GizmoEnabled>,
+    overlay: Res<GizmoOverlay>,
+    q_gizmo: Query<&GizmoBuiltOverlay, With<TransformGizmo>>,
+) -> bool {
+    q_gizmo
+        .single()
+        .is_ok_and(|built| !enabled.0 || *built != GizmoBuiltOverlay::from(&*overlay))
+}
+
+/// System sets of the plugin, which apps can order their own systems against. They only run while
+/// the Gizmo is [`GizmoEnabled`].
+///
+/// In `Update` they run in the order `Input`, `Picking`, `Manipulate` and `Render`. In `PostUpdate`,
+/// `Normalize` runs before [`TransformSystem::TransformPropagate`] so the Gizmo is drawn at its
+/// new size in the same frame, and `Render` after it to copy the final camera Transform.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GizmoSystems {
+    /// Keyboard input: cancelling drags, typed values and starting modal operations.
+    Input,
+    /// Scene queries under the cursor: snapping, and attaching custom handles to the Gizmo.
+    Picking,
+    /// Changes to the Transforms of the selection and the Gizmo, including validation.
+    Manipulate,
+    /// Scale of the Gizmo relative to the camera.
+    Normalize,
+    /// Drag guides, the swept angle and readout in `Update`, the overlay camera in `PostUpdate`.
+    Render,
+}
+
+/// Selects which picking backend reports hits on the gizmo handles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoPickingBackend {
+    /// Registers the [`MeshPickingPlugin`] (unless the app already did) and lets it pick the
+    /// gizmo handles as well as the rest of the scene. Apps that add the [`MeshPickingPlugin`]
+    /// themselves have to add it before this plugin.
+    #[default]
+    Mesh,
+    /// Registers the [`MeshPickingPlugin`] (unless the app already did) but restricts it to the
+    /// gizmo handles by enabling [`MeshPickingSettings::require_markers`]. Scene selection is left
+    /// to whichever backend the app uses (sprites, physics colliders, custom).
+    HandlesOnly,
+    /// Registers no backend at all. The app is responsible for a backend that reports hits on the
+    /// gizmo handle meshes, which live on the render layer of the [`GizmoOverlay`] and are seen by
+    /// [`InternalGizmoCamera`].
+    External,
+}
+
 #[derive(Resource)]
 pub struct TransformGizmoResource {
     pub entity: Option<Entity>,
@@ -40,8 +158,14 @@ pub struct TransformGizmoResource {
     pub origin: Option<GlobalTransform>,
     pub use_tag_filter: bool,
     pub selection_color: Color,
-    pub selection_button: MouseButton,
+    pub selection_button: PointerButton,
     pub drag_button: PointerButton,
+    pub pivot: GizmoPivot,
+    pub modes: GizmoModes,
+    pub space: GizmoSpace,
+    pub increments: GizmoIncrements,
+    /// Key held when starting a drag to move only the Gizmo and edit the pivot of the selection.
+    pub pivot_edit_modifier: KeyCode,
 }
 
 impl Default for TransformGizmoResource {
@@ -52,8 +176,13 @@ impl Default for TransformGizmoResource {
             origin: None,
             use_tag_filter: true,
             selection_color: Color::from(YELLOW_300),
-            selection_button: MouseButton::Left,
+            selection_button: PointerButton::Primary,
             drag_button: PointerButton::Primary,
+            pivot: GizmoPivot::default(),
+            modes: GizmoModes::default(),
+            space: GizmoSpace::default(),
+            increments: GizmoIncrements::default(),
+            pivot_edit_modifier: KeyCode::ControlLeft,
         }
     }
 }
@@ -61,8 +190,17 @@ impl Default for TransformGizmoResource {
 pub struct TransformGizmoPlugin {
     pub use_tag_filter: bool,
     pub selection_color: Color,
-    pub selection_button: MouseButton,
+    pub selection_button: PointerButton,
     pub drag_button: PointerButton,
+    pub picking_backend: GizmoPickingBackend,
+    pub pivot: GizmoPivot,
+    pub modes: GizmoModes,
+    pub space: GizmoSpace,
+    pub increments: GizmoIncrements,
+    pub layout: GizmoLayout,
+    /// Initial value of [`GizmoEnabled`].
+    pub enabled: bool,
+    pub overlay: GizmoOverlay,
 }
 
 impl Default for TransformGizmoPlugin {
@@ -70,8 +208,16 @@ impl Default for TransformGizmoPlugin {
         Self {
             use_tag_filter: false,
             selection_color: Color::from(YELLOW_300),
-            selection_button: MouseButton::Left,
+            selection_button: PointerButton::Primary,
             drag_button: PointerButton::Primary,
+            picking_backend: GizmoPickingBackend::default(),
+            pivot: GizmoPivot::default(),
+            modes: GizmoModes::default(),
+            space: GizmoSpace::default(),
+            increments: GizmoIncrements::default(),
+            layout: GizmoLayout::default(),
+            enabled: true,
+            overlay: GizmoOverlay::default(),
         }
     }
 }
@@ -89,49 +235,180 @@ impl Plugin for TransformGizmoPlugin {
             use_tag_filter: self.use_tag_filter,
             selection_color: self.selection_color,
             selection_button: self.selection_button,
+            pivot: self.pivot,
+            modes: self.modes,
+            space: self.space,
+            increments: self.increments,
             ..Default::default()
         };
 
         app.insert_resource(resource);
+        app.insert_resource(self.layout);
+        app.insert_resource(self.overlay);
+        app.insert_resource(GizmoEnabled(self.enabled));
+        app.init_resource::<GizmoInputBlocked>();
+        app.init_resource::<GizmoFloatingOrigin>();
 
-        app.add_plugins(MeshPickingPlugin);
         app.add_plugins(MaterialPlugin::<GizmoMaterial>::default());
+        // Apps that configure the backend themselves add it before this plugin
+        if self.picking_backend != GizmoPickingBackend::External
+            && !app.is_plugin_added::<MeshPickingPlugin>()
+        {
+            app.add_plugins(MeshPickingPlugin);
+        }
 
-        app.add_systems(PostStartup, build_gizmo);
         app.add_systems(
+            PreUpdate,
+            (
+                // An outdated Gizmo is rebuilt in the same frame
+                despawn_gizmo.run_if(gizmo_outdated),
+                (
+                    build_gizmo
+                        .run_if(gizmo_enabled.and(not(any_with_component::<TransformGizmo>))),
+                    spawn_target_gizmos.run_if(gizmo_enabled),
+                ),
+            )
+                .chain(),
+        );
+        app.add_observer(despawn_target_gizmo);
+        app.add_observer(select_on_click);
+        app.init_resource::<GizmoDrag>();
+        app.init_resource::<GizmoSnapping>();
+        app.add_event::<GizmoDragCancelled>();
+        app.add_observer(drag_start);
+        app.add_observer(drag_handle);
+        app.add_observer(drag_end);
+        app.insert_gizmo_config(
+            TransformGizmoLines,
+            TransformGizmoLines::config(self.overlay.render_layers()),
+        );
+        #[cfg(feature = "readout")]
+        app.init_resource::<readout::GizmoReadoutSettings>();
+        app.init_resource::<GizmoModal>();
+        app.init_resource::<GizmoModalKeys>();
+        app.configure_sets(
             Update,
-            transform_gizmo_picking_1
-                .pipe(transform_gizmo_picking_2)
-                .pipe(transform_gizmo_picking_3),
+            (
+                GizmoSystems::Input,
+                GizmoSystems::Picking,
+                GizmoSystems::Manipulate,
+                GizmoSystems::Render,
+            )
+                .chain()
+                .run_if(gizmo_enabled),
         );
-        app.add_systems(PostUpdate, normalize);
-        app.add_systems(PostUpdate, gizmo_cam_copy_settings);
+        app.configure_sets(
+            PostUpdate,
+            (
+                GizmoSystems::Normalize.before(TransformSystem::TransformPropagate),
+                GizmoSystems::Render.after(TransformSystem::TransformPropagate),
+            )
+                .run_if(gizmo_enabled),
+        );
+        app.add_systems(
+            Update,
+            (
+                (
+                    (cancel_drag, drag_numeric_input).chain(),
+                    modal_start.run_if(gizmo_input_allowed),
+                )
+                    .in_set(GizmoSystems::Input),
+                (attach_gizmo_handles, snap_drag).in_set(GizmoSystems::Picking),
+                (
+                    apply_drag,
+                    modal_update.run_if(gizmo_input_allowed),
+                    validate_transform.after(apply_drag).after(modal_update),
+                    place_gizmo_at_pivot.after(modal_update),
+                    hide_disallowed_handles.after(place_gizmo_at_pivot),
+                )
+                    .in_set(GizmoSystems::Manipulate),
+                (
+                    draw_drag_guides,
+                    update_swept_angle_pie,
+                    #[cfg(feature = "readout")]
+                    readout::update_readout,
+                )
+                    .in_set(GizmoSystems::Render),
+            ),
+        );
+        app.add_systems(PostUpdate, normalize.in_set(GizmoSystems::Normalize));
+        app.add_systems(
+            PostUpdate,
+            gizmo_cam_copy_settings.in_set(GizmoSystems::Render),
+        );
+    }
+
+    // Markers are required once all plugins were built, so app settings can't undo it
+    fn finish(&self, app: &mut App) {
+        if self.picking_backend == GizmoPickingBackend::HandlesOnly {
+            app.world_mut()
+                .resource_mut::<MeshPickingSettings>()
+                .require_markers = true;
+        }
     }
 }
 
+/// Copies the Transform, projection and rendering settings (order, viewport, HDR, Msaa,
+/// tonemapping) of the main camera to the [`InternalGizmoCamera`], so the overlay matches it.
+/// The [`GizmoOverlay`] may override some of them.
 #[allow(clippy::type_complexity)]
 fn gizmo_cam_copy_settings(
-    main_cam: Query<(Ref<Camera>, Ref<GlobalTransform>, Ref<Projection>), With<GizmoPickSource>>,
+    overlay: Res<GizmoOverlay>,
+    main_cam: Query<
+        (
+            Ref<Camera>,
+            Ref<GlobalTransform>,
+            Ref<Projection>,
+            Ref<Msaa>,
+            Option<Ref<Tonemapping>>,
+        ),
+        With<GizmoPickSource>,
+    >,
     gizmo_cam: Single<
-        (&mut Camera, &mut GlobalTransform, &mut Projection),
-        (With<InternalGizmoCamera>, Without<GizmoPickSource>),
+        (
+            &mut Camera,
+            &mut GlobalTransform,
+            &mut Projection,
+            &mut Msaa,
+            &mut Tonemapping,
+            Ref<InternalGizmoCamera>,
+        ),
+        Without<GizmoPickSource>,
     >,
 ) {
-    let (main_cam, main_cam_pos, main_proj) = if let Ok(x) = main_cam.single() {
+    let (main_cam, main_cam_pos, main_proj, main_msaa, main_tonemapping) = if let Ok(x) =
+        main_cam.single()
+    {
         x
     } else {
         error!("No `GizmoPickSource` found! Insert the `GizmoPickSource` component onto your primary 3d camera");
         return;
     };
-    let (mut gizmo_cam, mut gizmo_cam_pos, mut proj) = gizmo_cam.into_inner();
-    if main_cam_pos.is_changed() {
+    let (mut gizmo_cam, mut gizmo_cam_pos, mut proj, mut msaa, mut tonemapping, marker) =
+        gizmo_cam.into_inner();
+    // A rebuilt Gizmo camera copies everything once, new overrides apply right away
+    let added = marker.is_added();
+    let overridden = added || overlay.is_changed();
+    if added || main_cam_pos.is_changed() {
         *gizmo_cam_pos = *main_cam_pos;
     }
-    if main_cam.is_changed() {
+    if overridden || main_cam.is_changed() {
         *gizmo_cam = main_cam.clone();
-        gizmo_cam.order += 10;
+        gizmo_cam.order += overlay.order_offset;
     }
-    if main_proj.is_changed() {
+    if added || main_proj.is_changed() {
         *proj = main_proj.clone();
     }
+    if overridden || main_msaa.is_changed() {
+        *msaa = overlay.msaa.unwrap_or(*main_msaa);
+    }
+    let main_tonemapping_changed = main_tonemapping
+        .as_ref()
+        .is_some_and(|main_tonemapping| main_tonemapping.is_changed());
+    if overridden || main_tonemapping_changed {
+        *tonemapping = overlay
+            .tonemapping
+            .or(main_tonemapping.map(|main_tonemapping| *main_tonemapping))
+            .unwrap_or_default();
+    }
 }
@@ -6,7 +6,8 @@ use bevy::{
     render::{
         mesh::MeshVertexBufferLayoutRef,
         render_resource::{
-            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+            AsBindGroup, CompareFunction, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError,
         },
     },
 };
@@ -14,16 +15,49 @@ use bevy::{
 pub const GIZMO_SHADER_HANDLE: Handle<Shader> =
     weak_handle!("1a3a9469-c2d6-45b3-a0b6-5b1b03fb75fe");
 
+/// Which parts of a [`GizmoMaterial`] are drawn, depending on the depth of the scene.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GizmoDepthCompare {
+    /// The parts in front of the scene, like any other mesh.
+    #[default]
+    Visible,
+    /// Only the parts occluded by the scene, without writing depth.
+    Occluded,
+}
+
 #[derive(AsBindGroup, Asset, Clone, Debug, TypePath)]
+#[bind_group_data(GizmoMaterialKey)]
 pub struct GizmoMaterial {
     #[uniform(0)]
     pub color: LinearRgba,
+    pub alpha_mode: AlphaMode,
+    pub depth_compare: GizmoDepthCompare,
+}
+
+/// Pipeline key of a [`GizmoMaterial`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GizmoMaterialKey {
+    depth_compare: GizmoDepthCompare,
+}
+
+impl From<&GizmoMaterial> for GizmoMaterialKey {
+    fn from(material: &GizmoMaterial) -> Self {
+        Self {
+            depth_compare: material.depth_compare,
+        }
+    }
 }
 
 impl From<Color> for GizmoMaterial {
     fn from(color: Color) -> Self {
         GizmoMaterial {
             color: color.into(),
+            alpha_mode: if color.alpha() < 1.0 {
+                AlphaMode::Blend
+            } else {
+                AlphaMode::Opaque
+            },
+            depth_compare: GizmoDepthCompare::Visible,
         }
     }
 }
@@ -38,16 +72,23 @@ impl Material for GizmoMaterial {
     }
 
     fn alpha_mode(&self) -> AlphaMode {
-        AlphaMode::Opaque
+        self.alpha_mode
     }
 
     fn specialize(
         _pipeline: &MaterialPipeline<Self>,
         descriptor: &mut RenderPipelineDescriptor,
         _layout: &MeshVertexBufferLayoutRef,
-        _key: MaterialPipelineKey<Self>,
+        key: MaterialPipelineKey<Self>,
     ) -> Result<(), SpecializedMeshPipelineError> {
         descriptor.primitive.cull_mode = None;
+        if key.bind_group_data.depth_compare == GizmoDepthCompare::Occluded {
+            if let Some(depth_stencil) = descriptor.depth_stencil.as_mut() {
+                // Reverse Z, occluded fragments are farther than the scene
+                depth_stencil.depth_compare = CompareFunction::Less;
+                depth_stencil.depth_write_enabled = false;
+            }
+        }
         Ok(())
     }
 }
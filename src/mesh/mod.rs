@@ -1,4 +1,5 @@
 pub mod gizmo_material;
 
 pub mod cone;
+pub mod pie;
 pub mod truncated_torus;
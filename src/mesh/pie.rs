@@ -0,0 +1,67 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+/// A filled circle segment in the XZ plane, sweeping from +X around +Y.
+#[derive(Debug, Clone, Copy)]
+pub struct Pie {
+    pub radius: f32,
+    pub subdivisions: usize,
+    /// Swept angle in radians, negative angles sweep clockwise.
+    pub angle: f32,
+}
+
+impl Default for Pie {
+    fn default() -> Self {
+        Pie {
+            radius: 1.0,
+            subdivisions: 32,
+            angle: std::f32::consts::PI / 2.0,
+        }
+    }
+}
+
+impl From<Pie> for Mesh {
+    fn from(pie: Pie) -> Self {
+        let n_vertices = pie.subdivisions + 2;
+        let mut positions: Vec<[f32; 3]> = Vec::with_capacity(n_vertices);
+        let mut normals: Vec<[f32; 3]> = Vec::with_capacity(n_vertices);
+        let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(n_vertices);
+
+        let side_stride = pie.angle / pie.subdivisions as f32;
+
+        // Center
+        positions.push([0.0, 0.0, 0.0]);
+        normals.push(Vec3::Y.into());
+        uvs.push([0.5, 0.5]);
+
+        for side in 0..=pie.subdivisions {
+            let phi = side_stride * side as f32;
+            // Rotating +X around +Y moves it towards -Z
+            let x = phi.cos();
+            let z = -phi.sin();
+
+            positions.push([x * pie.radius, 0.0, z * pie.radius]);
+            normals.push(Vec3::Y.into());
+            uvs.push([0.5 + x * 0.5, 0.5 + z * 0.5]);
+        }
+
+        let mut indices: Vec<u32> = Vec::with_capacity(pie.subdivisions * 3);
+
+        for point in 1..pie.subdivisions + 1 {
+            indices.push(0);
+            indices.push(point as u32);
+            indices.push(point as u32 + 1);
+        }
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        mesh.insert_indices(Indices::U32(indices));
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh
+    }
+}
@@ -0,0 +1,557 @@
+use bevy::{prelude::*, render::primitives::Aabb};
+
+use crate::*;
+
+/// Keys that start and constrain keyboard driven modal operations.
+///
+/// Press an operation key to translate, rotate or scale the selection from the cursor, press an
+/// axis key to constrain the operation to the global axis (twice for the local axis, three times
+/// to remove the constraint), type a number for an exact value, `Enter` to confirm and `Escape`
+/// or the right mouse button to cancel.
+#[derive(Resource, Clone, Debug)]
+pub struct GizmoModalKeys {
+    pub translate: KeyCode,
+    pub rotate: KeyCode,
+    pub scale: KeyCode,
+    pub axis_x: KeyCode,
+    pub axis_y: KeyCode,
+    pub axis_z: KeyCode,
+}
+
+impl Default for GizmoModalKeys {
+    fn default() -> Self {
+        Self {
+            translate: KeyCode::KeyG,
+            rotate: KeyCode::KeyR,
+            scale: KeyCode::KeyS,
+            axis_x: KeyCode::KeyX,
+            axis_y: KeyCode::KeyY,
+            axis_z: KeyCode::KeyZ,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModalOperation {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// Axis constraint of a modal operation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ModalConstraint {
+    #[default]
+    None,
+    /// Constrained to a world space axis.
+    Global(Vec3),
+    /// Constrained to an axis of the selection, given in its local space.
+    Local(Vec3),
+}
+
+impl ModalConstraint {
+    /// Cycles global -> local -> unconstrained when the same axis key is pressed repeatedly.
+    fn cycle(self, axis: Vec3) -> Self {
+        match self {
+            ModalConstraint::Global(current) if current == axis => ModalConstraint::Local(axis),
+            ModalConstraint::Local(current) if current == axis => ModalConstraint::None,
+            _ => ModalConstraint::Global(axis),
+        }
+    }
+
+    /// Returns the constraint axis in world space.
+    pub fn world_axis(&self, rotation: Quat) -> Option<Vec3> {
+        match *self {
+            ModalConstraint::None => None,
+            ModalConstraint::Global(axis) => Some(axis),
+            ModalConstraint::Local(axis) => Some(rotation * axis),
+        }
+    }
+
+    /// Returns the constraint axis in the local space of the selection, given its world space
+    /// `rotation`.
+    pub fn local_axis(&self, rotation: Quat) -> Option<Vec3> {
+        match *self {
+            ModalConstraint::None => None,
+            ModalConstraint::Global(axis) => Some(rotation.inverse() * axis),
+            ModalConstraint::Local(axis) => Some(axis),
+        }
+    }
+}
+
+/// A modal operation in progress.
+#[derive(Clone, Debug)]
+pub struct ActiveModal {
+    pub operation: ModalOperation,
+    pub constraint: ModalConstraint,
+    /// The number typed so far, overriding the cursor movement once it parses.
+    pub input: NumericInput,
+    pub start: TransformSnapshot,
+    pub start_cursor: Vec2,
+}
+
+/// Number typed on the keyboard to enter an exact value. Components are separated by commas.
+#[derive(Clone, Debug, Default)]
+pub struct NumericInput {
+    pub text: String,
+}
+
+impl NumericInput {
+    /// Applies the keys pressed this frame. Returns `true` if the text changed.
+    pub fn update(&mut self, keys: &ButtonInput<KeyCode>) -> bool {
+        let mut changed = false;
+        for key in keys.get_just_pressed() {
+            match *key {
+                KeyCode::Backspace => {
+                    changed |= self.text.pop().is_some();
+                }
+                KeyCode::Minus | KeyCode::NumpadSubtract => {
+                    // Toggle the sign of the component that is being typed
+                    let component_start = self.text.rfind(',').map_or(0, |index| index + 1);
+                    if self.text[component_start..].starts_with('-') {
+                        self.text.remove(component_start);
+                    } else {
+                        self.text.insert(component_start, '-');
+                    }
+                    changed = true;
+                }
+                key => {
+                    if let Some(character) = numeric_char(key) {
+                        self.text.push(character);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns the typed components, or `None` while nothing was typed or a component is invalid.
+    /// Components that are still empty count as zero.
+    pub fn values(&self) -> Option<Vec<f32>> {
+        if self.text.is_empty() {
+            return None;
+        }
+        self.text
+            .split(',')
+            .map(|component| match component {
+                "" | "-" => Ok(0.),
+                component => component.parse(),
+            })
+            .collect::<Result<_, _>>()
+            .ok()
+    }
+
+    /// Returns the first typed component.
+    pub fn value(&self) -> Option<f32> {
+        self.values().map(|values| values[0])
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct GizmoModal {
+    pub active: Option<ActiveModal>,
+}
+
+/// Starts a modal operation when one of the [`GizmoModalKeys`] is pressed with a selection.
+#[allow(clippy::too_many_arguments)]
+pub fn modal_start(
+    keys: Res<ButtonInput<KeyCode>>,
+    modal_keys: Res<GizmoModalKeys>,
+    mut modal: ResMut<GizmoModal>,
+    selection: Res<TransformGizmoResource>,
+    q_options: Query<&GizmoOptions>,
+    drag_state: Res<GizmoDrag>,
+    q_gizmo: Single<Entity, With<TransformGizmo>>,
+    q_local_transform: Query<&mut Transform>,
+    parents: ParentTransforms,
+    windows: Single<&Window>,
+) {
+    if modal.active.is_some() || drag_state.active.is_some() || selection.entity.is_none() {
+        return;
+    }
+
+    let operation = if keys.just_pressed(modal_keys.translate) {
+        ModalOperation::Translate
+    } else if keys.just_pressed(modal_keys.rotate) {
+        ModalOperation::Rotate
+    } else if keys.just_pressed(modal_keys.scale) {
+        ModalOperation::Scale
+    } else {
+        return;
+    };
+    if !selection
+        .resolve_selection(&q_options)
+        .modes
+        .allows_modal(operation)
+    {
+        return;
+    }
+
+    let Some(start_cursor) = windows.cursor_position() else {
+        return;
+    };
+    let Some(start) =
+        TransformSnapshot::capture(&q_local_transform, &parents, *q_gizmo, selection.entity)
+    else {
+        return;
+    };
+
+    modal.active = Some(ActiveModal {
+        operation,
+        constraint: ModalConstraint::None,
+        input: NumericInput::default(),
+        start,
+        start_cursor,
+    });
+}
+
+/// Updates the running modal operation from the keyboard and cursor, and confirms or cancels it.
+#[allow(clippy::too_many_arguments)]
+pub fn modal_update(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    modal_keys: Res<GizmoModalKeys>,
+    mut modal: ResMut<GizmoModal>,
+    mut q_local_transform: Query<&mut Transform>,
+    q_transform: Query<&GlobalTransform>,
+    windows: Single<&Window>,
+    q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
+    q_constraints: Query<(&GizmoConstraints, Option<&Aabb>)>,
+    settings: Res<TransformGizmoResource>,
+    q_options: Query<&GizmoOptions>,
+) {
+    let Some(active) = modal.active.as_mut() else {
+        return;
+    };
+
+    // Cancel and restore the Transforms from before the operation
+    if keys.just_pressed(KeyCode::Escape) || mouse_input.just_pressed(MouseButton::Right) {
+        active.start.restore(&mut q_local_transform);
+        modal.active = None;
+        return;
+    }
+
+    for (key, axis) in [
+        (modal_keys.axis_x, Vec3::X),
+        (modal_keys.axis_y, Vec3::Y),
+        (modal_keys.axis_z, Vec3::Z),
+    ] {
+        if keys.just_pressed(key) {
+            active.constraint = active.constraint.cycle(axis);
+        }
+    }
+
+    active.input.update(&keys);
+
+    // The cursor drives the operation unless a value is typed. Without it the last result stays,
+    // but typed values and confirming still work
+    let cursor_position = windows.cursor_position();
+    if cursor_position.is_some() || active.input.values().is_some() {
+        // Only read on the pointer path, which requires the cursor
+        let cursor_position = cursor_position.unwrap_or(active.start_cursor);
+        let (camera_entity, camera) = *q_camera;
+        let camera_transform = q_transform.get(camera_entity).unwrap();
+
+        // Always start from the Transforms before the operation, so the result doesn't accumulate
+        active.start.restore(&mut q_local_transform);
+
+        let (gizmo, gizmo_start) = active.start.gizmo;
+        let selection = active
+            .start
+            .selection
+            .map(|(entity, _)| (entity, &active.start.selection_parent));
+        let pivot = gizmo_start.translation;
+        // Local constraints follow the selection, which the Gizmo isn't aligned with in World space
+        let sel_rotation = active.start.selection_rotation();
+        let axis = active.constraint.world_axis(sel_rotation);
+        let increments = settings
+            .resolve(selection.and_then(|(entity, _)| q_options.get(entity).ok()))
+            .increments;
+        let constraints = active
+            .start
+            .selection
+            .and_then(|(entity, sel_start)| Some((q_constraints.get(entity).ok()?.0, sel_start)));
+
+        match active.operation {
+            ModalOperation::Translate => {
+                let offset = match active.input.values() {
+                    Some(values) => match axis {
+                        Some(axis) => axis * values[0],
+                        // Unconstrained values are typed as "x,y,z"
+                        None => values
+                            .iter()
+                            .zip([Vec3::X, Vec3::Y, Vec3::Z])
+                            .fold(Vec3::ZERO, |offset, (value, axis)| offset + axis * *value),
+                    },
+                    None => {
+                        let offset = modal_translation(
+                            camera,
+                            camera_transform,
+                            active.start_cursor,
+                            cursor_position,
+                            pivot,
+                            axis,
+                        )
+                        .unwrap_or_default();
+                        match axis {
+                            Some(axis) => increments.snap_translation(offset, &[axis]),
+                            None => {
+                                increments.snap_translation(offset, &[Vec3::X, Vec3::Y, Vec3::Z])
+                            }
+                        }
+                    }
+                };
+                let offset = constraints.map_or(offset, |(constraints, sel_start)| {
+                    constraints
+                        .constrain(
+                            &active.start.selection_parent,
+                            &sel_start,
+                            offset,
+                            Quat::IDENTITY,
+                        )
+                        .0
+                });
+                apply_translation(&mut q_local_transform, gizmo, selection, offset);
+            }
+            ModalOperation::Rotate => {
+                let view_axis = Vec3::from(camera_transform.back());
+                let rotation_axis = axis.unwrap_or(view_axis);
+                let angle = match active.input.value() {
+                    Some(degrees) => degrees.to_radians(),
+                    None => {
+                        modal_screen_angle(
+                            camera,
+                            camera_transform,
+                            active.start_cursor,
+                            cursor_position,
+                            pivot,
+                        )
+                        .unwrap_or_default()
+                            // Keep the rotation following the cursor when the axis points away
+                            * rotation_axis.dot(view_axis).signum()
+                    }
+                };
+                let rotation = Quat::from_axis_angle(rotation_axis, angle);
+                let rotation = match active.input.value() {
+                    Some(_) => rotation,
+                    None => increments.snap_rotation(rotation, rotation_axis),
+                };
+                let rotation = constraints.map_or(rotation, |(constraints, sel_start)| {
+                    constraints
+                        .constrain(
+                            &active.start.selection_parent,
+                            &sel_start,
+                            Vec3::ZERO,
+                            rotation,
+                        )
+                        .1
+                });
+                apply_rotation(&mut q_local_transform, gizmo, selection, rotation);
+            }
+            ModalOperation::Scale => {
+                let factor = match active.input.value() {
+                    Some(value) => value,
+                    None => increments.snap_scale(
+                        modal_scale_factor(
+                            camera,
+                            camera_transform,
+                            active.start_cursor,
+                            cursor_position,
+                            pivot,
+                        )
+                        .unwrap_or(1.),
+                    ),
+                };
+                // Scaling always happens along the local axes of the selection, a global axis
+                // scales along the axes of the selection that match it
+                let scale = match active.constraint.local_axis(sel_rotation) {
+                    None => Vec3::splat(factor),
+                    Some(local_axis) => Vec3::ONE + local_axis.abs() * (factor - 1.),
+                };
+                let scale = constraints
+                    .map_or(scale, |(constraints, _)| constraints.constrain_scale(scale));
+                apply_scale(&mut q_local_transform, gizmo, selection, scale);
+            }
+        }
+        clamp_selection(&mut q_local_transform, &q_constraints, gizmo, selection);
+    }
+
+    if keys.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        modal.active = None;
+    }
+}
+
+fn numeric_char(key: KeyCode) -> Option<char> {
+    match key {
+        KeyCode::Digit0 | KeyCode::Numpad0 => Some('0'),
+        KeyCode::Digit1 | KeyCode::Numpad1 => Some('1'),
+        KeyCode::Digit2 | KeyCode::Numpad2 => Some('2'),
+        KeyCode::Digit3 | KeyCode::Numpad3 => Some('3'),
+        KeyCode::Digit4 | KeyCode::Numpad4 => Some('4'),
+        KeyCode::Digit5 | KeyCode::Numpad5 => Some('5'),
+        KeyCode::Digit6 | KeyCode::Numpad6 => Some('6'),
+        KeyCode::Digit7 | KeyCode::Numpad7 => Some('7'),
+        KeyCode::Digit8 | KeyCode::Numpad8 => Some('8'),
+        KeyCode::Digit9 | KeyCode::Numpad9 => Some('9'),
+        KeyCode::Period | KeyCode::NumpadDecimal => Some('.'),
+        KeyCode::Comma | KeyCode::NumpadComma => Some(','),
+        _ => None,
+    }
+}
+
+/// World space offset between the start and the current cursor position, measured on a plane
+/// through the pivot that faces the camera (or contains the constraint axis).
+fn modal_translation(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    start_cursor: Vec2,
+    cursor_position: Vec2,
+    pivot: Vec3,
+    axis: Option<Vec3>,
+) -> Option<Vec3> {
+    let view_normal = camera_transform.back();
+    let normal = match axis {
+        Some(axis) => Dir3::new(axis.cross(*view_normal).cross(axis)).unwrap_or(view_normal),
+        None => view_normal,
+    };
+    let plane = InfinitePlane3d::new(normal);
+
+    let ray = camera
+        .viewport_to_world(camera_transform, cursor_position)
+        .ok()?;
+    let point = ray.get_point(ray.intersect_plane(pivot, plane)?);
+
+    let ray_start = camera
+        .viewport_to_world(camera_transform, start_cursor)
+        .ok()?;
+    let point_start = ray_start.get_point(ray_start.intersect_plane(pivot, plane)?);
+
+    let delta_vector = point - point_start;
+    Some(match axis {
+        Some(axis) => delta_vector.project_onto(axis),
+        None => delta_vector,
+    })
+}
+
+/// Counter-clockwise angle the cursor travelled around the pivot on screen.
+fn modal_screen_angle(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    start_cursor: Vec2,
+    cursor_position: Vec2,
+    pivot: Vec3,
+) -> Option<f32> {
+    let pivot_screen = camera.world_to_viewport(camera_transform, pivot).ok()?;
+    // Viewport coordinates point down, flip them to get a counter-clockwise angle
+    let flip = Vec2::new(1., -1.);
+    let from = (start_cursor - pivot_screen) * flip;
+    let to = (cursor_position - pivot_screen) * flip;
+    if from == Vec2::ZERO || to == Vec2::ZERO {
+        return None;
+    }
+    Some(from.angle_to(to))
+}
+
+/// Ratio between the current and the start distance of the cursor to the pivot on screen.
+fn modal_scale_factor(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    start_cursor: Vec2,
+    cursor_position: Vec2,
+    pivot: Vec3,
+) -> Option<f32> {
+    let pivot_screen = camera.world_to_viewport(camera_transform, pivot).ok()?;
+    let start_distance = start_cursor.distance(pivot_screen);
+    if start_distance <= f32::EPSILON {
+        return None;
+    }
+    Some(cursor_position.distance(pivot_screen) / start_distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(keys: &[KeyCode]) -> NumericInput {
+        let mut input = NumericInput::default();
+        let mut button_input = ButtonInput::<KeyCode>::default();
+        // One key per frame, keys pressed in the same frame come in no particular order
+        for key in keys {
+            button_input.press(*key);
+            input.update(&button_input);
+            button_input.release(*key);
+            button_input.clear();
+        }
+        input
+    }
+
+    #[test]
+    fn parses_comma_separated_components() {
+        let input = NumericInput {
+            text: "1.5,-2,".into(),
+        };
+        assert_eq!(input.values(), Some(vec![1.5, -2., 0.]));
+        assert_eq!(input.value(), Some(1.5));
+    }
+
+    #[test]
+    fn empty_and_invalid_text_has_no_values() {
+        assert_eq!(NumericInput::default().values(), None);
+        let input = NumericInput {
+            text: "1..2".into(),
+        };
+        assert_eq!(input.values(), None);
+        let input = NumericInput { text: "-".into() };
+        assert_eq!(input.values(), Some(vec![0.]));
+    }
+
+    #[test]
+    fn typing_toggles_the_sign_of_the_current_component() {
+        let input = typed(&[
+            KeyCode::Digit1,
+            KeyCode::Period,
+            KeyCode::Numpad5,
+            KeyCode::Comma,
+            KeyCode::Digit2,
+            KeyCode::Minus,
+        ]);
+        assert_eq!(input.text, "1.5,-2");
+        let input = typed(&[
+            KeyCode::Digit3,
+            KeyCode::Minus,
+            KeyCode::Minus,
+            KeyCode::Backspace,
+        ]);
+        assert_eq!(input.text, "");
+    }
+
+    #[test]
+    fn constraint_cycles_global_local_none() {
+        let constraint = ModalConstraint::None.cycle(Vec3::X);
+        assert_eq!(constraint, ModalConstraint::Global(Vec3::X));
+        let constraint = constraint.cycle(Vec3::X);
+        assert_eq!(constraint, ModalConstraint::Local(Vec3::X));
+        assert_eq!(constraint.cycle(Vec3::Y), ModalConstraint::Global(Vec3::Y));
+        assert_eq!(constraint.cycle(Vec3::X), ModalConstraint::None);
+    }
+
+    #[test]
+    fn global_constraint_axis_in_selection_space() {
+        // Local Z of the selection points along world X
+        let rotation = Quat::from_rotation_y(90f32.to_radians());
+        let local_axis = ModalConstraint::Global(Vec3::X)
+            .local_axis(rotation)
+            .unwrap();
+        assert!(local_axis.abs().abs_diff_eq(Vec3::Z, 1e-5));
+        assert_eq!(
+            ModalConstraint::Local(Vec3::X).local_axis(rotation),
+            Some(Vec3::X)
+        );
+        assert_eq!(ModalConstraint::None.local_axis(rotation), None);
+    }
+}
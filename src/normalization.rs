@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, transform::helper::TransformHelper};
 
 use crate::*;
 
@@ -11,22 +11,28 @@ pub struct Normalize3d {
     pub desired_pixel_size: f32,
 }
 
-// This Function Scales the Gizmo always to the correct Size
-#[allow(clippy::type_complexity)]
+// This Function Scales the Gizmo always to the correct Size. It runs before transform propagation,
+// so the Global Transforms are computed from the Transforms of this frame.
 pub fn normalize(
     q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
-    mut q_transform: Query<(Entity, &mut Transform, &Normalize3d), With<Normalize3d>>,
-    q_global_transform: Query<&GlobalTransform>,
+    q_normalize: Query<(Entity, &Normalize3d)>,
+    mut transforms: ParamSet<(TransformHelper, Query<&mut Transform>)>,
 ) {
-    for (entity, mut transform, normalize) in q_transform.iter_mut() {
-        let (camera_entity, camera) = *q_camera;
-
-        let camera_transform = q_global_transform.get(camera_entity).unwrap();
-        let view = camera_transform.compute_matrix().inverse();
-
-        let global_transform = q_global_transform.get(entity).unwrap();
+    let (camera_entity, camera) = *q_camera;
+    let transform_helper = transforms.p0();
+    let Ok(camera_transform) = transform_helper.compute_global_transform(camera_entity) else {
+        return;
+    };
+    let mut scales = Vec::new();
+    for (entity, normalize) in &q_normalize {
+        let Ok(global_transform) = transform_helper.compute_global_transform(entity) else {
+            continue;
+        };
 
-        let distance = view.transform_point3(global_transform.translation()).z;
+        // Depth in view space, measured from the camera instead of through the inverse view
+        // matrix, which loses precision far from the world origin
+        let relative = global_transform.translation() - camera_transform.translation();
+        let distance = relative.dot(*camera_transform.back());
         let gt = global_transform.compute_transform();
 
         let Ok(pixel_root) = camera.world_to_viewport(
@@ -44,6 +50,13 @@ pub fn normalize(
 
         let actual_pixel_size = pixel_root.distance(pixel_end);
         let required_scale = normalize.desired_pixel_size / actual_pixel_size;
-        transform.scale = gt.scale * Vec3::splat(required_scale);
+        scales.push((entity, gt.scale * Vec3::splat(required_scale)));
+    }
+
+    let mut q_transform = transforms.p1();
+    for (entity, scale) in scales {
+        if let Ok(mut transform) = q_transform.get_mut(entity) {
+            transform.scale = scale;
+        }
     }
 }
@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+
+use crate::*;
+
+/// Operations the Gizmo may perform on an Entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GizmoModes {
+    pub translate: bool,
+    pub rotate: bool,
+    pub scale: bool,
+}
+
+impl GizmoModes {
+    pub const ALL: Self = Self {
+        translate: true,
+        rotate: true,
+        scale: true,
+    };
+
+    /// Returns whether the operation of a Gizmo handle is allowed.
+    pub fn allows(&self, handle: GizmoHandle) -> bool {
+        match handle {
+            GizmoHandle::Axis(_) | GizmoHandle::Plane(_) | GizmoHandle::ViewPlane => self.translate,
+            GizmoHandle::Ring(_) => self.rotate,
+            GizmoHandle::Scale(_) => self.scale,
+        }
+    }
+
+    /// Returns whether a modal operation is allowed.
+    pub fn allows_modal(&self, operation: ModalOperation) -> bool {
+        match operation {
+            ModalOperation::Translate => self.translate,
+            ModalOperation::Rotate => self.rotate,
+            ModalOperation::Scale => self.scale,
+        }
+    }
+}
+
+impl Default for GizmoModes {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Orientation of the Gizmo handles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoSpace {
+    /// Aligned with the selected Entity.
+    #[default]
+    Local,
+    /// Aligned with the world axes.
+    World,
+}
+
+/// Steps that dragged values snap to, relative to the start of the drag. `None` keeps the value
+/// continuous. Typed values are used as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GizmoIncrements {
+    /// Distance in world units.
+    pub translation: Option<f32>,
+    /// Angle in degrees.
+    pub rotation: Option<f32>,
+    /// Step of the scale factor.
+    pub scale: Option<f32>,
+}
+
+impl GizmoIncrements {
+    /// Snaps the components of `translation` along each of the `axes`, which are expected to be
+    /// orthonormal.
+    pub fn snap_translation(&self, translation: Vec3, axes: &[Vec3]) -> Vec3 {
+        let Some(step) = self.translation.filter(|step| *step > 0.) else {
+            return translation;
+        };
+        axes.iter()
+            .map(|axis| *axis * snap(translation.dot(*axis), step))
+            .sum()
+    }
+
+    /// Snaps the angle of `rotation` around `axis`.
+    pub fn snap_rotation(&self, rotation: Quat, axis: Vec3) -> Quat {
+        let Some(step) = self.rotation.filter(|step| *step > 0.) else {
+            return rotation;
+        };
+        let angle = signed_angle(rotation, axis);
+        Quat::from_axis_angle(axis, snap(angle, step.to_radians()))
+    }
+
+    /// Snaps a scale factor. It never snaps to zero, which would collapse the selection, but to
+    /// one step in the direction of the factor instead.
+    pub fn snap_scale(&self, factor: f32) -> f32 {
+        match self.scale.filter(|step| *step > 0.) {
+            Some(step) => match snap(factor, step) {
+                0. => step.copysign(factor),
+                snapped => snapped,
+            },
+            None => factor,
+        }
+    }
+}
+
+fn snap(value: f32, step: f32) -> f32 {
+    (value / step).round() * step
+}
+
+/// Overrides the global settings of [`TransformGizmoResource`] while the Entity it is inserted on
+/// is selected. Fields left at `None` use the global setting.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct GizmoOptions {
+    pub modes: Option<GizmoModes>,
+    pub space: Option<GizmoSpace>,
+    pub increments: Option<GizmoIncrements>,
+    pub pivot: Option<GizmoPivot>,
+}
+
+/// The global Gizmo settings merged with the [`GizmoOptions`] of an Entity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResolvedGizmoOptions {
+    pub modes: GizmoModes,
+    pub space: GizmoSpace,
+    pub increments: GizmoIncrements,
+    pub pivot: GizmoPivot,
+}
+
+impl TransformGizmoResource {
+    /// Merges the global settings with the [`GizmoOptions`] of an Entity.
+    pub fn resolve(&self, options: Option<&GizmoOptions>) -> ResolvedGizmoOptions {
+        let options = options.copied().unwrap_or_default();
+        ResolvedGizmoOptions {
+            modes: options.modes.unwrap_or(self.modes),
+            space: options.space.unwrap_or(self.space),
+            increments: options.increments.unwrap_or(self.increments),
+            pivot: options.pivot.unwrap_or(self.pivot),
+        }
+    }
+
+    /// Returns the settings for the selected Entity.
+    pub fn resolve_selection(&self, q_options: &Query<&GizmoOptions>) -> ResolvedGizmoOptions {
+        self.resolve(self.entity.and_then(|entity| q_options.get(entity).ok()))
+    }
+}
+
+/// Hides the Gizmo handles that the [`GizmoModes`] or the [`GizmoConstraints`] of the Entity their
+/// Gizmo transforms disallow.
+pub fn hide_disallowed_handles(
+    selection: Res<TransformGizmoResource>,
+    q_options: Query<&GizmoOptions>,
+    q_constraints: Query<&GizmoConstraints>,
+    q_instances: Query<&TransformGizmoInstance>,
+    q_local_transform: Query<&Transform>,
+    parents: ParentTransforms,
+    mut q_handles: Query<(&GizmoHandle, &ChildOf, &mut Visibility)>,
+) {
+    for (handle, parent, mut visibility) in &mut q_handles {
+        let gizmo = parent.parent();
+        let sel_entity = selection.selection_of(gizmo, &q_instances);
+        let modes = selection
+            .resolve(sel_entity.and_then(|entity| q_options.get(entity).ok()))
+            .modes;
+        let constraints = sel_entity.and_then(|entity| q_constraints.get(entity).ok());
+        // Constraints are given in the local space of the selection, which the Gizmo may not be
+        // aligned with. The Gizmo root is in world space, the selection relative to its parent.
+        let to_selection = sel_entity
+            .and_then(|entity| {
+                let sel_transform = q_local_transform.get(entity).ok()?;
+                Some(parents.get(entity).rotation() * sel_transform.rotation)
+            })
+            .zip(q_local_transform.get(gizmo).ok())
+            .map_or(Quat::IDENTITY, |(sel_rotation, gizmo_transform)| {
+                sel_rotation.inverse() * gizmo_transform.rotation
+            });
+
+        let allowed = modes.allows(*handle)
+            && constraints
+                .is_none_or(|constraints| constraints.allows(handle.rotated(to_selection)));
+        visibility.set_if_neq(if allowed {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_translation_along_axes() {
+        let increments = GizmoIncrements {
+            translation: Some(0.5),
+            ..default()
+        };
+        let snapped = increments.snap_translation(Vec3::new(0.7, 1.3, 9.), &[Vec3::X, Vec3::Y]);
+        assert_eq!(snapped, Vec3::new(0.5, 1.5, 0.));
+    }
+
+    #[test]
+    fn snap_rotation_to_steps() {
+        let increments = GizmoIncrements {
+            rotation: Some(15.),
+            ..default()
+        };
+        let rotation = increments.snap_rotation(Quat::from_rotation_y(20f32.to_radians()), Vec3::Y);
+        assert!(rotation.angle_between(Quat::from_rotation_y(15f32.to_radians())) < 1e-3);
+        let rotation =
+            increments.snap_rotation(Quat::from_rotation_y(-37f32.to_radians()), Vec3::Y);
+        assert!(rotation.angle_between(Quat::from_rotation_y(-30f32.to_radians())) < 1e-3);
+    }
+
+    #[test]
+    fn snap_scale_never_reaches_zero() {
+        let increments = GizmoIncrements {
+            scale: Some(0.5),
+            ..default()
+        };
+        assert_eq!(increments.snap_scale(1.3), 1.5);
+        assert_eq!(increments.snap_scale(0.1), 0.5);
+        assert_eq!(increments.snap_scale(0.), 0.5);
+        assert_eq!(increments.snap_scale(-0.1), -0.5);
+    }
+
+    #[test]
+    fn no_increments_keep_values() {
+        let increments = GizmoIncrements::default();
+        assert_eq!(increments.snap_scale(0.1), 0.1);
+        assert_eq!(
+            increments.snap_translation(Vec3::splat(0.3), &[Vec3::X]),
+            Vec3::splat(0.3)
+        );
+    }
+}
@@ -2,91 +2,76 @@ use bevy::prelude::*;
 
 use crate::*;
 
-pub fn transform_gizmo_picking_1(
-    windows: Single<&Window>,
-    q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
+/// This Observer Function selects the clicked Entity, the [`TransformGizmo`] is then attached to it
+/// by [`place_gizmo_at_pivot`].
+///
+/// Selection is driven by [`Pointer<Click>`] events, so any picking backend that reports hits
+/// (mesh, sprite, physics colliders, custom) can select an Entity. Nothing is ray cast while
+/// the pointer is idle. When the tag filter is enabled, the click bubbles up the hierarchy
+/// until it reaches an Entity marked with [`GizmoTransformable`].
+#[allow(clippy::too_many_arguments)]
+pub fn select_on_click(
+    mut click: Trigger<Pointer<Click>>,
+    mut materials_3d: Query<&mut MeshMaterial3d<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut gizmo_resource: ResMut<TransformGizmoResource>,
     q_transform: Query<&GlobalTransform>,
-) -> Option<Ray3d> {
-    let (camera_entity, camera) = *q_camera;
-    let camera_transform = q_transform.get(camera_entity).unwrap();
-    let cursor_position = windows.cursor_position()?;
-    camera
-        .viewport_to_world(camera_transform, cursor_position)
-        .ok()
-}
-
-pub fn transform_gizmo_picking_2(
-    In(ray): In<Option<Ray3d>>,
-    mut ray_cast: MeshRayCast,
-    gizmo_resource: Res<TransformGizmoResource>,
     q_tagged: Query<(), With<GizmoTransformable>>,
-    q_gizmo_parts: Query<(), Without<TransformGizmoPart>>,
-) -> Option<Entity> {
-    let filter_gizmo_parts = |entity| q_gizmo_parts.contains(entity);
-
-    let filter = |entity| q_tagged.contains(entity);
-
-    // Never early-exit. Note that you can change behavior per-entity.
-    let early_exit_test = |_entity| false;
-
-    // Ignore the visibility of entities. This allows ray casting hidden entities.
-    let visibility = RayCastVisibility::Any;
+    q_gizmo_parts: Query<(), With<TransformGizmoPart>>,
+    blocked: Res<GizmoInputBlocked>,
+    enabled: Res<GizmoEnabled>,
+    modal: Res<GizmoModal>,
+) {
+    if click.button != gizmo_resource.selection_button || blocked.0 || !enabled.0 {
+        return;
+    }
+    // A modal operation keeps editing the selection until it is confirmed or cancelled
+    if modal.active.is_some() {
+        click.propagate(false);
+        return;
+    }
 
-    let mut settings = MeshRayCastSettings::default()
-        .with_filter(&filter_gizmo_parts)
-        .with_early_exit_test(&early_exit_test)
-        .with_visibility(visibility);
+    let hit_entity = click.target();
 
-    // Allow only tagged Components to be found;
-    if gizmo_resource.use_tag_filter {
-        settings = settings.with_filter(&filter);
+    // Clicks on the Gizmo itself never change the selection
+    if q_gizmo_parts.contains(hit_entity) {
+        click.propagate(false);
+        return;
     }
 
-    ray_cast
-        .cast_ray(ray?, &settings)
-        .first()
-        .map(|(hit_entity, _)| hit_entity)
-        .copied()
-}
+    // Allow only tagged Components to be selected, keep bubbling to a tagged parent otherwise
+    if gizmo_resource.use_tag_filter && !q_tagged.contains(hit_entity) {
+        return;
+    }
 
-pub fn transform_gizmo_picking_3(
-    In(hit_entity): In<Option<Entity>>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
-    mut materials_3d: Query<&mut MeshMaterial3d<StandardMaterial>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut gizmo_resource: ResMut<TransformGizmoResource>,
-    mut q_gizmo: Single<&mut Transform, With<TransformGizmo>>,
-    q_transform: Query<&GlobalTransform>,
-) {
-    let Some(hit_entity) = hit_entity else {
+    // Windows and other Entities without a position can't carry the Gizmo
+    let Ok(sel_transform) = q_transform.get(hit_entity) else {
         return;
     };
-    if mouse_input.just_released(gizmo_resource.selection_button) {
-        if let Some(last_selection) = gizmo_resource.entity {
-            // Reset Last Selection
-
-            let mut material = materials_3d.get_mut(last_selection).unwrap();
-            material.0 = gizmo_resource.original_color.clone().unwrap();
-
-            gizmo_resource.origin = None;
-            gizmo_resource.entity = None;
-            gizmo_resource.original_color = None;
+    click.propagate(false);
+
+    if let Some(last_selection) = gizmo_resource.entity {
+        // Reset Last Selection
+        if let (Ok(mut material), Some(original_color)) = (
+            materials_3d.get_mut(last_selection),
+            gizmo_resource.original_color.clone(),
+        ) {
+            material.0 = original_color;
         }
 
-        let mut material = materials_3d.get_mut(hit_entity).unwrap();
-        // Store the active Entity
+        gizmo_resource.origin = None;
+        gizmo_resource.entity = None;
+        gizmo_resource.original_color = None;
+    }
 
-        gizmo_resource.entity = Some(hit_entity);
+    // Store the active Entity
+    gizmo_resource.entity = Some(hit_entity);
+    gizmo_resource.origin = Some(*sel_transform);
+
+    if let Ok(mut material) = materials_3d.get_mut(hit_entity) {
         gizmo_resource.original_color = Some(material.0.clone());
-        gizmo_resource.origin = Some(*q_transform.get(hit_entity).unwrap());
 
         let pressed_matl = materials.add(gizmo_resource.selection_color);
         material.0 = pressed_matl;
-
-        // Attach the TransformGizmo to it
-        let sel_tranform = *q_transform.get(hit_entity).unwrap();
-
-        **q_gizmo = Transform::from_translation(sel_tranform.translation())
-            .with_rotation(sel_tranform.rotation());
     }
 }
@@ -0,0 +1,113 @@
+use bevy::{ecs::system::SystemParam, prelude::*, render::primitives::Aabb};
+
+use crate::*;
+
+/// Point the Gizmo is placed on, and that rotations and scales happen around.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoPivot {
+    /// Origin of the selected Entity.
+    #[default]
+    Origin,
+    /// Center of the [`Aabb`] of the selected Entity, its origin if it has none.
+    BoundsCenter,
+    /// The Entity marked with [`GizmoCursor3d`], the origin of the selection if there is none.
+    Cursor3d,
+    /// An arbitrary Entity, the origin of the selection if it doesn't exist.
+    Entity(Entity),
+}
+
+/// Pivot point of an Entity in its local space, overriding [`GizmoPivot::Origin`] and
+/// [`GizmoPivot::BoundsCenter`] for it.
+///
+/// Inserted by dragging the Gizmo while holding [`TransformGizmoResource::pivot_edit_modifier`],
+/// which moves only the Gizmo. Remove it to return to the global pivot mode.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct GizmoPivotOffset(pub Vec3);
+
+/// Marks the user-placeable 3D cursor used as pivot by [`GizmoPivot::Cursor3d`].
+#[derive(Component)]
+pub struct GizmoCursor3d;
+
+/// Resolves [`GizmoPivot`] settings to world space points.
+#[derive(SystemParam)]
+pub struct PivotPoints<'w, 's> {
+    q_transform: Query<'w, 's, &'static GlobalTransform>,
+    q_aabb: Query<'w, 's, &'static Aabb>,
+    q_offset: Query<'w, 's, &'static GizmoPivotOffset>,
+    q_cursor: Query<'w, 's, &'static GlobalTransform, With<GizmoCursor3d>>,
+}
+
+impl PivotPoints<'_, '_> {
+    /// Returns the world space pivot point of `selection`.
+    pub fn get(&self, pivot: GizmoPivot, selection: Entity) -> Option<Vec3> {
+        let selection_transform = self.q_transform.get(selection).ok()?;
+        let offset = self.q_offset.get(selection).ok();
+        let pivot_point = match (pivot, offset) {
+            (GizmoPivot::Origin | GizmoPivot::BoundsCenter, Some(offset)) => {
+                Some(selection_transform.transform_point(offset.0))
+            }
+            (GizmoPivot::Origin, None) => None,
+            (GizmoPivot::BoundsCenter, None) => self
+                .q_aabb
+                .get(selection)
+                .ok()
+                .map(|aabb| selection_transform.transform_point(aabb.center.into())),
+            (GizmoPivot::Cursor3d, _) => self
+                .q_cursor
+                .iter()
+                .next()
+                .map(GlobalTransform::translation),
+            (GizmoPivot::Entity(entity), _) => self
+                .q_transform
+                .get(entity)
+                .ok()
+                .map(GlobalTransform::translation),
+        };
+        Some(pivot_point.unwrap_or(selection_transform.translation()))
+    }
+}
+
+/// Keeps the Gizmo on the pivot of the selection, and every Gizmo instance on the pivot of its
+/// [`TransformGizmoTarget`], aligned with it or the world depending on the [`GizmoSpace`], while
+/// nothing is dragged.
+#[allow(clippy::type_complexity)]
+pub fn place_gizmo_at_pivot(
+    selection: Res<TransformGizmoResource>,
+    q_options: Query<&GizmoOptions>,
+    drag_state: Res<GizmoDrag>,
+    modal: Res<GizmoModal>,
+    pivots: PivotPoints,
+    q_transform: Query<&GlobalTransform>,
+    mut q_gizmos: Query<
+        (&mut Transform, Option<&TransformGizmoInstance>),
+        Or<(With<TransformGizmo>, With<TransformGizmoInstance>)>,
+    >,
+) {
+    if drag_state.active.is_some() || modal.active.is_some() {
+        return;
+    }
+    for (mut gizmo_transform, instance) in &mut q_gizmos {
+        let Some(sel_entity) = instance
+            .map(|instance| instance.target)
+            .or(selection.entity)
+        else {
+            continue;
+        };
+        let options = selection.resolve(q_options.get(sel_entity).ok());
+        let (Some(translation), Ok(sel_transform)) = (
+            pivots.get(options.pivot, sel_entity),
+            q_transform.get(sel_entity),
+        ) else {
+            continue;
+        };
+
+        let rotation = match options.space {
+            GizmoSpace::Local => sel_transform.rotation(),
+            GizmoSpace::World => Quat::IDENTITY,
+        };
+        if gizmo_transform.translation != translation || gizmo_transform.rotation != rotation {
+            gizmo_transform.translation = translation;
+            gizmo_transform.rotation = rotation;
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use bevy::prelude::*;
+
+use crate::*;
+
+/// Marker for the text node that shows the progress of a drag or a modal operation.
+#[derive(Component)]
+pub struct GizmoReadout;
+
+/// Where the readout is placed on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadoutAnchor {
+    /// Next to the cursor.
+    #[default]
+    Cursor,
+    /// Next to the origin of the Gizmo being operated.
+    Gizmo,
+}
+
+/// Settings of the on-screen readout shown during drags and modal operations.
+#[derive(Resource, Clone, Debug)]
+pub struct GizmoReadoutSettings {
+    /// Show the delta and the resulting Transform, not only typed values.
+    pub show_transform: bool,
+    pub anchor: ReadoutAnchor,
+    /// Offset from the anchor in logical pixels.
+    pub offset: Vec2,
+    /// Suffix of displayed distances.
+    pub distance_unit: String,
+    /// Factor from world units to the displayed unit, e.g. `100.0` to show meters as centimeters.
+    pub distance_scale: f32,
+    /// Number of decimals of displayed values.
+    pub precision: usize,
+    pub font_size: f32,
+}
+
+impl Default for GizmoReadoutSettings {
+    fn default() -> Self {
+        Self {
+            show_transform: true,
+            anchor: ReadoutAnchor::default(),
+            offset: Vec2::new(16.0, 16.0),
+            distance_unit: String::from("m"),
+            distance_scale: 1.0,
+            precision: 2,
+            font_size: 16.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ReadoutKind {
+    Translate,
+    /// Rotation, with the world space axis that positive angles are measured around if known.
+    Rotate(Option<Vec3>),
+    Scale,
+}
+
+/// Shows the delta, the resulting Transform and typed values of the drag or modal operation in
+/// progress next to the cursor or the Gizmo.
+#[allow(clippy::too_many_arguments)]
+pub fn update_readout(
+    mut commands: Commands,
+    settings: Res<GizmoReadoutSettings>,
+    drag_state: Res<GizmoDrag>,
+    modal: Res<GizmoModal>,
+    windows: Single<&Window>,
+    q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
+    q_transform: Query<&GlobalTransform>,
+    q_local_transform: Query<&Transform>,
+    mut q_readout: Query<
+        (&mut Text, &mut TextFont, &mut Node, &mut Visibility),
+        With<GizmoReadout>,
+    >,
+) {
+    let operation = if let Some(active) = drag_state
+        .active
+        .as_ref()
+        .filter(|active| !active.cancelled)
+    {
+        active.constraint.map(|constraint| {
+            let kind = match constraint {
+                DragConstraint::Rotation(axis) => ReadoutKind::Rotate(Some(axis)),
+                DragConstraint::Scale(..) => ReadoutKind::Scale,
+                _ => ReadoutKind::Translate,
+            };
+            (kind, &active.start, &active.input)
+        })
+    } else if let Some(active) = modal.active.as_ref() {
+        let kind = match active.operation {
+            ModalOperation::Translate => ReadoutKind::Translate,
+            ModalOperation::Rotate => ReadoutKind::Rotate(None),
+            ModalOperation::Scale => ReadoutKind::Scale,
+        };
+        Some((kind, &active.start, &active.input))
+    } else {
+        None
+    };
+
+    let label = operation.and_then(|(kind, start, input)| {
+        let mut lines = Vec::new();
+        if !input.is_empty() {
+            lines.push(format!("Typed  {}", input.text));
+        }
+        if settings.show_transform {
+            // The Gizmo root has no parent
+            let (parent, (entity, start)) = start
+                .selection
+                .map(|selection| (start.selection_parent, selection))
+                .unwrap_or((GlobalTransform::IDENTITY, start.gizmo));
+            if let Ok(current) = q_local_transform.get(entity) {
+                lines.extend(transform_lines(&settings, kind, &parent, &start, current));
+            }
+        }
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    });
+
+    let gizmo = operation.map(|(_, start, _)| start.gizmo.0);
+    let anchor = match settings.anchor {
+        ReadoutAnchor::Cursor => windows.cursor_position(),
+        ReadoutAnchor::Gizmo => {
+            let (camera_entity, camera) = *q_camera;
+            q_transform
+                .get(camera_entity)
+                .ok()
+                .zip(gizmo.and_then(|gizmo| q_transform.get(gizmo).ok()))
+                .and_then(|(camera_transform, gizmo_transform)| {
+                    camera
+                        .world_to_viewport(camera_transform, gizmo_transform.translation())
+                        .ok()
+                })
+        }
+    };
+
+    let Ok((mut text, mut font, mut node, mut visibility)) = q_readout.single_mut() else {
+        // Spawn the readout the first time there is something to show
+        if let (Some(label), Some(anchor)) = (label, anchor) {
+            let position = anchor + settings.offset;
+            commands.spawn((
+                Text::new(label),
+                TextFont::from_font_size(settings.font_size),
+                TextColor(Color::WHITE),
+                BackgroundColor(Color::BLACK.with_alpha(0.6)),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(position.x),
+                    top: Val::Px(position.y),
+                    padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                    ..default()
+                },
+                Pickable::IGNORE,
+                GizmoReadout,
+            ));
+        }
+        return;
+    };
+
+    let (Some(label), Some(anchor)) = (label, anchor) else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+
+    let position = anchor + settings.offset;
+    visibility.set_if_neq(Visibility::Inherited);
+    node.left = Val::Px(position.x);
+    node.top = Val::Px(position.y);
+    if font.font_size != settings.font_size {
+        font.font_size = settings.font_size;
+    }
+    if text.0 != label {
+        text.0 = label;
+    }
+}
+
+/// Formats the delta between the start and the current Transform, and the current Transform. Both
+/// are local to `parent` and shown in world space, like the axis of [`ReadoutKind::Rotate`].
+fn transform_lines(
+    settings: &GizmoReadoutSettings,
+    kind: ReadoutKind,
+    parent: &GlobalTransform,
+    start: &Transform,
+    current: &Transform,
+) -> [String; 2] {
+    let start = parent.mul_transform(*start).compute_transform();
+    let current = parent.mul_transform(*current).compute_transform();
+    let precision = settings.precision;
+    let unit = &settings.distance_unit;
+    let distance = |value: f32| value * settings.distance_scale;
+    match kind {
+        ReadoutKind::Translate => {
+            let delta = current.translation - start.translation;
+            let position = current.translation;
+            [
+                format!(
+                    "Δ  X {:.precision$}  Y {:.precision$}  Z {:.precision$} {unit}",
+                    distance(delta.x),
+                    distance(delta.y),
+                    distance(delta.z),
+                ),
+                format!(
+                    "Position  {:.precision$}, {:.precision$}, {:.precision$} {unit}",
+                    distance(position.x),
+                    distance(position.y),
+                    distance(position.z),
+                ),
+            ]
+        }
+        ReadoutKind::Rotate(axis) => {
+            let delta = current.rotation * start.rotation.inverse();
+            let angle = signed_angle(delta, axis.unwrap_or(delta.to_axis_angle().0));
+            let (x, y, z) = current.rotation.to_euler(EulerRot::XYZ);
+            [
+                format!("Δ  {:.precision$}°", angle.to_degrees()),
+                format!(
+                    "Rotation  X {:.precision$}°  Y {:.precision$}°  Z {:.precision$}°",
+                    x.to_degrees(),
+                    y.to_degrees(),
+                    z.to_degrees(),
+                ),
+            ]
+        }
+        ReadoutKind::Scale => {
+            let delta = current.scale / start.scale;
+            let scale = current.scale;
+            [
+                format!(
+                    "Δ  ×{:.precision$}, {:.precision$}, {:.precision$}",
+                    delta.x, delta.y, delta.z,
+                ),
+                format!(
+                    "Scale  {:.precision$}, {:.precision$}, {:.precision$}",
+                    scale.x, scale.y, scale.z,
+                ),
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_lines_of_parented_selections_in_world_space() {
+        let settings = GizmoReadoutSettings {
+            precision: 0,
+            ..default()
+        };
+        // Local X of the selection points along world -Z through its parent
+        let parent = GlobalTransform::from(
+            Transform::from_xyz(0., 1., 0.)
+                .with_rotation(Quat::from_rotation_y(90f32.to_radians())),
+        );
+        let start = Transform::IDENTITY;
+        let moved = Transform::from_xyz(2., 0., 0.);
+        let [delta, position] =
+            transform_lines(&settings, ReadoutKind::Translate, &parent, &start, &moved);
+        assert!(delta.contains("X 0  Y 0  Z -2"), "{delta}");
+        assert!(position.contains("0, 1, -2"), "{position}");
+
+        // Rotating around world X is rotating around local Z of the parent
+        let rotated = Transform::from_rotation(Quat::from_rotation_z(30f32.to_radians()));
+        let [delta, _] = transform_lines(
+            &settings,
+            ReadoutKind::Rotate(Some(Vec3::X)),
+            &parent,
+            &start,
+            &rotated,
+        );
+        assert!(delta.contains("Δ  30°"), "{delta}");
+    }
+}
@@ -0,0 +1,616 @@
+use bevy::{
+    ecs::system::SystemParam,
+    math::Affine3A,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    render::{
+        mesh::{PrimitiveTopology, VertexAttributeValues},
+        primitives::Aabb,
+        view::RenderLayers,
+    },
+};
+
+use crate::*;
+
+/// Snapping settings of Gizmo drags.
+#[derive(Resource, Clone, Debug)]
+pub struct GizmoSnapping {
+    /// Place the selection on the scene geometry under the cursor during plane and view-plane
+    /// drags, instead of moving it through the air.
+    pub surface: bool,
+    /// Local axis of the selection that is aligned with the surface normal when snapping to
+    /// surfaces, `None` keeps the rotation of the selection.
+    pub align_to_normal: Option<Vec3>,
+    /// Features of other Entities the pivot of the selection snaps onto while translating.
+    pub features: SnapFeatures,
+    /// Distance from the cursor in logical pixels within which features are snapped onto.
+    pub feature_radius: f32,
+    /// Show guides and softly snap while translating when the center or the bounds of the
+    /// selection line up with those of another visible [`GizmoTransformable`] Entity.
+    pub alignment: bool,
+    /// Distance in world units within which the selection snaps into alignment.
+    pub alignment_distance: f32,
+    /// Distance in world units the selection has to be dragged out of an alignment to leave it.
+    /// Larger than `alignment_distance`, so an alignment doesn't flicker on and off at its edge.
+    pub alignment_release_distance: f32,
+    /// Held during a drag to move the selection freely, without aligning it.
+    pub alignment_bypass: Option<KeyCode>,
+}
+
+impl Default for GizmoSnapping {
+    fn default() -> Self {
+        Self {
+            surface: false,
+            align_to_normal: None,
+            features: SnapFeatures::NONE,
+            feature_radius: 12.0,
+            alignment: false,
+            alignment_distance: 0.1,
+            alignment_release_distance: 0.2,
+            alignment_bypass: Some(KeyCode::AltLeft),
+        }
+    }
+}
+
+/// Mesh features of other Entities that the selection can be snapped onto.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnapFeatures {
+    /// Vertices of the [`Mesh::ATTRIBUTE_POSITION`] attribute.
+    pub vertices: bool,
+    /// Midpoints of the triangle edges.
+    pub edge_midpoints: bool,
+    /// Corners of the [`Aabb`].
+    pub bounds_corners: bool,
+}
+
+impl SnapFeatures {
+    pub const NONE: Self = Self {
+        vertices: false,
+        edge_midpoints: false,
+        bounds_corners: false,
+    };
+    pub const ALL: Self = Self {
+        vertices: true,
+        edge_midpoints: true,
+        bounds_corners: true,
+    };
+
+    pub fn any(&self) -> bool {
+        self.vertices || self.edge_midpoints || self.bounds_corners
+    }
+}
+
+/// Returns `true` if `entity` is `selection` or one of its children.
+fn is_selection(q_parents: &Query<&ChildOf>, selection: Option<Entity>, entity: Entity) -> bool {
+    selection.is_some_and(|selection| {
+        std::iter::once(entity)
+            .chain(q_parents.iter_ancestors(entity))
+            .any(|ancestor| ancestor == selection)
+    })
+}
+
+/// Casts rays into the scene the main camera sees, ignoring the selection and the Gizmo.
+#[derive(SystemParam)]
+pub struct SnapRayCast<'w, 's> {
+    ray_cast: MeshRayCast<'w, 's>,
+    q_parents: Query<'w, 's, &'static ChildOf>,
+    q_layers: Query<'w, 's, &'static RenderLayers>,
+    q_gizmo_parts: Query<'w, 's, (), With<TransformGizmoPart>>,
+}
+
+impl SnapRayCast<'_, '_> {
+    /// Returns the closest hit point and its normalized surface normal.
+    pub fn cast(
+        &mut self,
+        ray: Ray3d,
+        selection: Option<Entity>,
+        camera_layers: &RenderLayers,
+    ) -> Option<(Vec3, Vec3)> {
+        let q_parents = &self.q_parents;
+        let q_layers = &self.q_layers;
+        let q_gizmo_parts = &self.q_gizmo_parts;
+        let filter = |entity| {
+            // Skip what the main camera doesn't see, and the Gizmo in case it renders it
+            let entity_layers = q_layers.get(entity).cloned().unwrap_or_default();
+            !is_selection(q_parents, selection, entity)
+                && !q_gizmo_parts.contains(entity)
+                && camera_layers.intersects(&entity_layers)
+        };
+        let settings = MeshRayCastSettings::default().with_filter(&filter);
+        self.ray_cast
+            .cast_ray(ray, &settings)
+            .first()
+            .map(|(_, hit)| (hit.point, hit.normal.normalize_or(Vec3::Y)))
+    }
+}
+
+/// Finds vertices, edge midpoints and bounding box corners of visible meshes close to the cursor.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct SnapFeatureQuery<'w, 's> {
+    q_meshes: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Mesh3d,
+            &'static GlobalTransform,
+            Option<&'static Aabb>,
+            &'static ViewVisibility,
+            Option<&'static RenderLayers>,
+        ),
+        Without<TransformGizmoPart>,
+    >,
+    q_parents: Query<'w, 's, &'static ChildOf>,
+    meshes: Res<'w, Assets<Mesh>>,
+    mesh_events: EventReader<'w, 's, AssetEvent<Mesh>>,
+    /// Local space midpoints of the unique triangle edges of every mesh snapped onto so far.
+    edge_midpoints: Local<'s, HashMap<AssetId<Mesh>, Vec<Vec3>>>,
+}
+
+impl SnapFeatureQuery<'_, '_> {
+    /// Forgets the cached edge midpoints of meshes that changed, or of all meshes while nothing is
+    /// dragged. Runs every frame so no change of a mesh is missed.
+    pub fn refresh_cache(&mut self, dragging: bool) {
+        if !dragging {
+            self.edge_midpoints.clear();
+            self.mesh_events.clear();
+            return;
+        }
+        for event in self.mesh_events.read() {
+            if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+                self.edge_midpoints.remove(id);
+            }
+        }
+    }
+
+    /// Returns the world space feature closest to `cursor_position` on screen, within `radius`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn closest(
+        &mut self,
+        features: SnapFeatures,
+        radius: f32,
+        cursor_position: Vec2,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        camera_layers: &RenderLayers,
+        selection: Option<Entity>,
+    ) -> Option<Vec3> {
+        let mut closest: Option<(f32, Vec3)> = None;
+        let mut consider = |point: Vec3| {
+            let Ok(screen_position) = camera.world_to_viewport(camera_transform, point) else {
+                return;
+            };
+            let distance = screen_position.distance(cursor_position);
+            if distance <= radius && closest.is_none_or(|(closest, _)| distance < closest) {
+                closest = Some((distance, point));
+            }
+        };
+
+        for (entity, mesh, transform, aabb, view_visibility, layers) in &self.q_meshes {
+            if !view_visibility.get()
+                || !camera_layers.intersects(layers.unwrap_or(&RenderLayers::default()))
+                || is_selection(&self.q_parents, selection, entity)
+            {
+                continue;
+            }
+            // Skip meshes whose bounds are too far from the cursor on screen to have any feature
+            // within the radius
+            if let Some(aabb) = aabb {
+                if !bounds_near_cursor(
+                    aabb,
+                    transform,
+                    camera,
+                    camera_transform,
+                    cursor_position,
+                    radius,
+                ) {
+                    continue;
+                }
+                if features.bounds_corners {
+                    for corner in box_corners(aabb.min().into(), aabb.max().into()) {
+                        consider(transform.transform_point(corner));
+                    }
+                }
+            }
+
+            if !(features.vertices || features.edge_midpoints) {
+                continue;
+            }
+            let Some(mesh_asset) = self.meshes.get(&mesh.0) else {
+                continue;
+            };
+            let Some(positions) = mesh_asset
+                .attribute(Mesh::ATTRIBUTE_POSITION)
+                .and_then(VertexAttributeValues::as_float3)
+            else {
+                continue;
+            };
+
+            if features.vertices {
+                for position in positions {
+                    consider(transform.transform_point((*position).into()));
+                }
+            }
+
+            if features.edge_midpoints {
+                let midpoints = self
+                    .edge_midpoints
+                    .entry(mesh.id())
+                    .or_insert_with(|| edge_midpoints(mesh_asset, positions));
+                for midpoint in midpoints.iter() {
+                    consider(transform.transform_point(*midpoint));
+                }
+            }
+        }
+
+        closest.map(|(_, point)| point)
+    }
+}
+
+/// Returns the local space midpoints of the unique triangle edges of a mesh.
+fn edge_midpoints(mesh: &Mesh, positions: &[[f32; 3]]) -> Vec<Vec3> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Vec::new();
+    }
+    let mut indices: Box<dyn Iterator<Item = usize>> = match mesh.indices() {
+        Some(indices) => Box::new(indices.iter()),
+        None => Box::new(0..positions.len()),
+    };
+    let mut edges = HashSet::new();
+    let mut midpoints = Vec::new();
+    while let (Some(a), Some(b), Some(c)) = (indices.next(), indices.next(), indices.next()) {
+        for (from, to) in [(a, b), (b, c), (c, a)] {
+            let (Some(from_position), Some(to_position)) = (positions.get(from), positions.get(to))
+            else {
+                continue;
+            };
+            // Edges shared by neighbouring triangles are only added once
+            if edges.insert((from.min(to), from.max(to))) {
+                midpoints.push(Vec3::from(*from_position).midpoint(Vec3::from(*to_position)));
+            }
+        }
+    }
+    midpoints
+}
+
+/// Returns the 8 corners of the box between `min` and `max`.
+fn box_corners(min: Vec3, max: Vec3) -> impl Iterator<Item = Vec3> {
+    (0..8).map(move |corner| {
+        Vec3::select(
+            BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+            max,
+            min,
+        )
+    })
+}
+
+/// Returns whether the bounds of a mesh come within `radius` of `cursor_position` on screen. Bounds
+/// reaching behind the camera can't be projected and are always near.
+fn bounds_near_cursor(
+    aabb: &Aabb,
+    transform: &GlobalTransform,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    cursor_position: Vec2,
+    radius: f32,
+) -> bool {
+    let [min, _, max] = transformed_bounds(&transform.affine(), Some(aabb));
+    let mut screen_bounds = Rect::EMPTY;
+    for corner in box_corners(min, max) {
+        let Ok(screen_position) = camera.world_to_viewport(camera_transform, corner) else {
+            return true;
+        };
+        screen_bounds = screen_bounds.union_point(screen_position);
+    }
+    screen_bounds.inflate(radius).contains(cursor_position)
+}
+
+/// Minimum, center and maximum of the bounds of an Entity in the space `affine` transforms into, its
+/// origin if it has no [`Aabb`].
+pub(crate) fn transformed_bounds(affine: &Affine3A, aabb: Option<&Aabb>) -> [Vec3; 3] {
+    let Some(aabb) = aabb else {
+        let origin = Vec3::from(affine.translation);
+        return [origin; 3];
+    };
+    let (center, half_extents) = (Vec3::from(aabb.center), Vec3::from(aabb.half_extents));
+    let matrix = affine.matrix3;
+    // Half extents of the box enclosing the transformed Aabb
+    let transformed_half_extents = Vec3::from(matrix.x_axis.abs()) * half_extents.x
+        + Vec3::from(matrix.y_axis.abs()) * half_extents.y
+        + Vec3::from(matrix.z_axis.abs()) * half_extents.z;
+    let transformed_center = affine.transform_point3(center);
+    [
+        transformed_center - transformed_half_extents,
+        transformed_center,
+        transformed_center + transformed_half_extents,
+    ]
+}
+
+/// Finds alignments of the bounds of the selection with other visible [`GizmoTransformable`]
+/// Entities.
+#[derive(SystemParam)]
+pub struct AlignmentQuery<'w, 's> {
+    q_targets: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static GlobalTransform,
+            Option<&'static Aabb>,
+            &'static ViewVisibility,
+        ),
+        With<GizmoTransformable>,
+    >,
+    q_bounds: Query<'w, 's, (&'static GlobalTransform, Option<&'static Aabb>)>,
+}
+
+impl AlignmentQuery<'_, '_> {
+    /// Moves `translation`, the world space offset of the Gizmo from `gizmo_start`, along the world
+    /// axes allowed by `constraint` to line up the selection with the closest other Entity on each
+    /// axis. It is within the alignment distance of the [`GizmoSnapping`], or its release distance
+    /// along the axes in `aligned`, which were aligned before. Returns the adjusted offset, the
+    /// guides to draw and the axes now aligned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn align(
+        &self,
+        selection: Entity,
+        gizmo: Entity,
+        gizmo_start: Vec3,
+        translation: Vec3,
+        constraint: DragConstraint,
+        snapping: &GizmoSnapping,
+        aligned: BVec3,
+    ) -> (Vec3, Vec<(Vec3, Vec3)>, BVec3) {
+        let (Ok((sel_transform, sel_aabb)), Ok((gizmo_transform, _))) =
+            (self.q_bounds.get(selection), self.q_bounds.get(gizmo))
+        else {
+            return (translation, Vec::new(), BVec3::FALSE);
+        };
+        // The Transforms of the selection and the Gizmo are from the same frame, so the offset
+        // between them holds for the proposed position too
+        let proposed = gizmo_start + translation - gizmo_transform.translation();
+        let sel_bounds =
+            transformed_bounds(&sel_transform.affine(), sel_aabb).map(|point| point + proposed);
+
+        let targets: Vec<[Vec3; 3]> = self
+            .q_targets
+            .iter()
+            .filter(|(entity, .., view_visibility)| *entity != selection && view_visibility.get())
+            .map(|(_, transform, aabb, _)| transformed_bounds(&transform.affine(), aabb))
+            .collect();
+
+        let mut translation = translation;
+        let mut guides = Vec::new();
+        let mut now_aligned = BVec3::FALSE;
+        for (index, world_axis) in [Vec3::X, Vec3::Y, Vec3::Z].into_iter().enumerate() {
+            let movable = match constraint {
+                DragConstraint::Axis(axis) => {
+                    axis.normalize_or_zero().dot(world_axis).abs() > 0.999
+                }
+                DragConstraint::Plane(axis_1, axis_2) => {
+                    axis_1
+                        .cross(axis_2)
+                        .normalize_or_zero()
+                        .dot(world_axis)
+                        .abs()
+                        < 0.001
+                }
+                DragConstraint::Rotation(_) | DragConstraint::Scale(..) => false,
+            };
+            if !movable {
+                continue;
+            }
+
+            let distance = if aligned.test(index) {
+                snapping.alignment_release_distance
+            } else {
+                snapping.alignment_distance
+            };
+            // Closest pair of a selection and a target coordinate along the axis
+            let mut best: Option<(f32, Vec3, Vec3)> = None;
+            for target in &targets {
+                for sel_point in &sel_bounds {
+                    for target_point in target {
+                        let difference = (*target_point - *sel_point).dot(world_axis);
+                        if difference.abs() <= distance
+                            && best.is_none_or(|(best, ..)| difference.abs() < best.abs())
+                        {
+                            best = Some((difference, *sel_point, *target_point));
+                        }
+                    }
+                }
+            }
+            let Some((difference, sel_point, target_point)) = best else {
+                continue;
+            };
+            translation += world_axis * difference;
+            now_aligned.set(index, true);
+            // The aligned points share their coordinate on the axis, so the guide is orthogonal
+            // to it
+            guides.push((sel_point + world_axis * difference, target_point));
+        }
+        (translation, guides, now_aligned)
+    }
+}
+
+/// Snaps the drag in progress according to [`GizmoSnapping`]. Features of other Entities close to
+/// the cursor take precedence over surfaces, alignments adjust whatever else was found.
+#[allow(clippy::too_many_arguments)]
+pub fn snap_drag(
+    snapping: Res<GizmoSnapping>,
+    mut drag_state: ResMut<GizmoDrag>,
+    mut snap_ray_cast: SnapRayCast,
+    mut snap_features: SnapFeatureQuery,
+    alignments: AlignmentQuery,
+    windows: Single<&Window>,
+    q_camera: Single<(Entity, &Camera, Option<&RenderLayers>), With<GizmoPickSource>>,
+    q_transform: Query<&GlobalTransform>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    snap_features.refresh_cache(drag_state.active.is_some());
+    let Some(active) = drag_state
+        .active
+        .as_ref()
+        .filter(|active| !active.cancelled && !active.pivot_edit)
+    else {
+        return;
+    };
+    let translating = matches!(
+        active.constraint,
+        Some(DragConstraint::Axis(_) | DragConstraint::Plane(..))
+    );
+    let snap_surface =
+        snapping.surface && matches!(active.constraint, Some(DragConstraint::Plane(..)));
+    let snap_to_features = translating && snapping.features.any();
+    let snap_alignment = translating
+        && snapping.alignment
+        && !snapping
+            .alignment_bypass
+            .is_some_and(|key| keys.pressed(key));
+    if !(snap_surface || snap_to_features || snap_alignment) {
+        if active.snapped.is_some()
+            || active.snap_target.is_some()
+            || !active.alignment_guides.is_empty()
+        {
+            let active = drag_state.active.as_mut().unwrap();
+            active.snapped = None;
+            active.snap_target = None;
+            active.alignment_guides.clear();
+            active.aligned_axes = BVec3::FALSE;
+        }
+        return;
+    }
+
+    let (camera_entity, camera, camera_layers) = *q_camera;
+    let camera_layers = camera_layers.unwrap_or(&RenderLayers::default()).clone();
+    let camera_transform = q_transform.get(camera_entity).unwrap();
+    let Some(cursor_position) = windows.cursor_position() else {
+        return;
+    };
+
+    let (gizmo, gizmo_start) = active.start.gizmo;
+    let selection = active.start.selection;
+    let selection_entity = selection.map(|(entity, _)| entity);
+
+    let snap_target = snap_to_features
+        .then(|| {
+            snap_features.closest(
+                snapping.features,
+                snapping.feature_radius,
+                cursor_position,
+                camera,
+                camera_transform,
+                &camera_layers,
+                selection_entity,
+            )
+        })
+        .flatten();
+
+    let snapped = if let Some(target) = snap_target {
+        let offset = target - gizmo_start.translation;
+        let translation = match active.constraint {
+            Some(DragConstraint::Axis(axis)) => offset.project_onto(axis),
+            _ => offset,
+        };
+        Some((translation, Quat::IDENTITY))
+    } else if snap_surface {
+        camera
+            .viewport_to_world(camera_transform, cursor_position)
+            .ok()
+            .and_then(|ray| snap_ray_cast.cast(ray, selection_entity, &camera_layers))
+            .map(|(point, normal)| {
+                let rotation = match (snapping.align_to_normal, selection) {
+                    (Some(axis), Some((_, sel_start))) => {
+                        let current_axis = (sel_start.rotation * axis).normalize_or(normal);
+                        Quat::from_rotation_arc(current_axis, normal)
+                    }
+                    _ => Quat::IDENTITY,
+                };
+                (point - gizmo_start.translation, rotation)
+            })
+    } else {
+        None
+    };
+
+    let (snapped, alignment_guides, aligned_axes) =
+        match (snap_alignment, snap_target, selection_entity) {
+            (true, None, Some(selection_entity)) => {
+                let (translation, rotation) =
+                    snapped.unwrap_or((active.translation, active.rotation));
+                let (aligned, guides, aligned_axes) = alignments.align(
+                    selection_entity,
+                    gizmo,
+                    gizmo_start.translation,
+                    translation,
+                    active.constraint.unwrap(),
+                    &snapping,
+                    active.aligned_axes,
+                );
+                let snapped = (!guides.is_empty())
+                    .then_some((aligned, rotation))
+                    .or(snapped);
+                (snapped, guides, aligned_axes)
+            }
+            _ => (snapped, Vec::new(), BVec3::FALSE),
+        };
+
+    if let Some(active) = drag_state.active.as_mut() {
+        active.snapped = snapped;
+        active.snap_target = snap_target;
+        active.alignment_guides = alignment_guides;
+        active.aligned_axes = aligned_axes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::RenderAssetUsages, render::mesh::Indices};
+
+    use super::*;
+
+    fn quad(indices: Option<Vec<u32>>) -> (Mesh, Vec<[f32; 3]>) {
+        let positions = vec![[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]];
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+        if let Some(indices) = indices {
+            mesh.insert_indices(Indices::U32(indices));
+        }
+        (mesh, positions)
+    }
+
+    #[test]
+    fn shared_edges_have_one_midpoint() {
+        let (mesh, positions) = quad(Some(vec![0, 1, 2, 0, 2, 3]));
+        let midpoints = edge_midpoints(&mesh, &positions);
+        assert_eq!(midpoints.len(), 5);
+        assert!(midpoints.contains(&Vec3::new(0.5, 0.5, 0.)));
+    }
+
+    #[test]
+    fn edge_midpoints_without_indices() {
+        let (mesh, positions) = quad(None);
+        // Only the first triangle is complete
+        let midpoints = edge_midpoints(&mesh, &positions);
+        assert_eq!(
+            midpoints,
+            vec![
+                Vec3::new(0.5, 0., 0.),
+                Vec3::new(1., 0.5, 0.),
+                Vec3::new(0.5, 0.5, 0.)
+            ]
+        );
+    }
+
+    #[test]
+    fn box_corners_are_unique() {
+        let corners: Vec<Vec3> = box_corners(Vec3::ZERO, Vec3::ONE).collect();
+        assert_eq!(corners.len(), 8);
+        for (index, corner) in corners.iter().enumerate() {
+            assert!(!corners[index + 1..].contains(corner));
+        }
+    }
+}
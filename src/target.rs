@@ -0,0 +1,176 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    ecs::system::{StaticSystemParam, SystemParam, SystemParamItem},
+    math::DVec3,
+    prelude::*,
+};
+
+use crate::*;
+
+/// Adapts data other than the [`Transform`] of the selected Entity to the Gizmo, like a double
+/// precision position or the direction of a light.
+///
+/// The Gizmo keeps editing the Transform of the selected Entity as a proxy of the pose. While
+/// idle, the pose read from the adapter is copied into it, and every pose the Gizmo proposes is
+/// written back through the adapter. Register an adapter with [`GizmoTargetPlugin`].
+///
+/// Only Entities with a Transform can be selected. Data without one of its own, like the control
+/// points of a spline, needs a proxy Entity per editable pose; the `spline_proxy` example adapts
+/// such proxies.
+pub trait GizmoTarget: Send + Sync + 'static {
+    /// Data the adapter reads and writes. It must not access [`Transform`].
+    type Param: SystemParam + 'static;
+
+    /// Returns the pose of `entity` in the space of its parent, or `None` if the adapter doesn't
+    /// handle it. `current` is its Transform, for adapters that only provide parts of the pose.
+    fn read(
+        entity: Entity,
+        current: &Transform,
+        param: &mut SystemParamItem<Self::Param>,
+    ) -> Option<Transform>;
+
+    /// Writes the pose the Gizmo proposes for `entity`.
+    fn write(entity: Entity, pose: &Transform, param: &mut SystemParamItem<Self::Param>);
+}
+
+/// Registers a [`GizmoTarget`] adapter.
+pub struct GizmoTargetPlugin<T: GizmoTarget>(PhantomData<T>);
+
+impl<T: GizmoTarget> Default for GizmoTargetPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: GizmoTarget> Plugin for GizmoTargetPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                read_target_pose::<T>
+                    .in_set(GizmoSystems::Input)
+                    .before(cancel_drag),
+                write_target_pose::<T>
+                    .in_set(GizmoSystems::Manipulate)
+                    .after(validate_transform),
+            ),
+        );
+        app.add_systems(
+            PreUpdate,
+            write_cancelled_target_pose::<T>
+                .before(despawn_gizmo)
+                .run_if(gizmo_outdated),
+        );
+    }
+}
+
+/// Copies the pose of the selection and of the [`TransformGizmoTarget`]s from the adapter into
+/// their Transforms while nothing is dragged.
+pub fn read_target_pose<T: GizmoTarget>(
+    selection: Res<TransformGizmoResource>,
+    drag_state: Res<GizmoDrag>,
+    modal: Res<GizmoModal>,
+    mut param: StaticSystemParam<T::Param>,
+    q_instances: Query<&TransformGizmoInstance>,
+    mut q_local_transform: Query<&mut Transform>,
+) {
+    if drag_state.active.is_some() || modal.active.is_some() {
+        return;
+    }
+    let targets = q_instances.iter().map(|instance| instance.target);
+    for sel_entity in selection.entity.into_iter().chain(targets) {
+        let Ok(mut sel_transform) = q_local_transform.get_mut(sel_entity) else {
+            continue;
+        };
+        if let Some(pose) = T::read(sel_entity, &sel_transform, &mut param) {
+            sel_transform.set_if_neq(pose);
+        }
+    }
+}
+
+/// Writes the Transform of the selection or a [`TransformGizmoTarget`] back through the adapter
+/// when a drag or modal operation changed it, including when it was cancelled.
+pub fn write_target_pose<T: GizmoTarget>(
+    selection: Res<TransformGizmoResource>,
+    drag_state: Res<GizmoDrag>,
+    modal: Res<GizmoModal>,
+    mut param: StaticSystemParam<T::Param>,
+    q_instances: Query<&TransformGizmoInstance>,
+    q_local_transform: Query<Ref<Transform>>,
+) {
+    let operating = drag_state.active.is_some()
+        || modal.active.is_some()
+        || drag_state.is_changed()
+        || modal.is_changed();
+    if !operating {
+        return;
+    }
+    let targets = q_instances.iter().map(|instance| instance.target);
+    for sel_entity in selection.entity.into_iter().chain(targets) {
+        if let Ok(sel_transform) = q_local_transform.get(sel_entity) {
+            if sel_transform.is_changed() {
+                T::write(sel_entity, &sel_transform, &mut param);
+            }
+        }
+    }
+}
+
+/// Writes the drag-start pose of the selection back through the adapter when disabling or
+/// rebuilding the Gizmo cancels the drag or modal operation in progress, see [`despawn_gizmo`].
+pub fn write_cancelled_target_pose<T: GizmoTarget>(
+    drag_state: Res<GizmoDrag>,
+    modal: Res<GizmoModal>,
+    mut param: StaticSystemParam<T::Param>,
+) {
+    let drag_start = drag_state.active.as_ref().map(|active| active.start);
+    let modal_start = modal.active.as_ref().map(|active| active.start);
+    for (sel_entity, pose) in drag_start
+        .into_iter()
+        .chain(modal_start)
+        .filter_map(|start| start.selection)
+    {
+        T::write(sel_entity, &pose, &mut param);
+    }
+}
+
+/// World space position of the render origin in floating origin setups, which keep the camera
+/// close to `Vec3::ZERO` and place Entities relative to it.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct GizmoFloatingOrigin(pub DVec3);
+
+/// Double precision world space position of an Entity, the translation of its Transform being
+/// relative to the [`GizmoFloatingOrigin`].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct GizmoWorldPosition(pub DVec3);
+
+/// [`GizmoTarget`] of Entities with a [`GizmoWorldPosition`]. The Gizmo works on the small
+/// translation relative to the floating origin, and the result is written back in double
+/// precision. Rotation and scale stay in the Transform.
+pub struct WorldPositionTarget;
+
+impl GizmoTarget for WorldPositionTarget {
+    type Param = (
+        Res<'static, GizmoFloatingOrigin>,
+        Query<'static, 'static, &'static mut GizmoWorldPosition>,
+    );
+
+    fn read(
+        entity: Entity,
+        current: &Transform,
+        (origin, q_position): &mut SystemParamItem<Self::Param>,
+    ) -> Option<Transform> {
+        let position = q_position.get(entity).ok()?;
+        Some(current.with_translation((position.0 - origin.0).as_vec3()))
+    }
+
+    fn write(
+        entity: Entity,
+        pose: &Transform,
+        (origin, q_position): &mut SystemParamItem<Self::Param>,
+    ) {
+        if let Ok(mut position) = q_position.get_mut(entity) {
+            position.0 = origin.0 + pose.translation.as_dvec3();
+        }
+    }
+}
@@ -1,327 +1,684 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*, render::primitives::Aabb};
 
 use crate::*;
 
-/// This Observer Function allows to move in the forward/Back direction of the dragged Entity  
-pub fn transform_axis(
-    drag: Trigger<Pointer<Drag>>,
-    q_parents: Query<&ChildOf>,
-    q_transform: Query<&GlobalTransform>,
-    mut q_local_transform: Query<&mut Transform>,
-    windows: Single<&Window>,
-    q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
-    selection: Res<TransformGizmoResource>,
-) {
-    // Check if the correct Mouse Button is pressed
-    if drag.button != selection.drag_button {
-        return;
-    }
-    let (camera_entity, camera) = *q_camera;
-
-    let handle_entity = drag.target();
-
-    let parent_entity = q_parents.get(handle_entity).unwrap().parent();
+/// Looks up the Global Transforms of parents, which local Transforms are relative to.
+#[derive(SystemParam)]
+pub struct ParentTransforms<'w, 's> {
+    q_parents: Query<'w, 's, &'static ChildOf>,
+    q_transform: Query<'w, 's, &'static GlobalTransform>,
+}
 
-    let gizmo_transform = q_transform.get(handle_entity).unwrap();
+impl ParentTransforms<'_, '_> {
+    /// Returns the Global Transform of the parent of `entity`, the identity without one.
+    pub fn get(&self, entity: Entity) -> GlobalTransform {
+        self.q_parents
+            .get(entity)
+            .and_then(|parent| self.q_transform.get(parent.parent()))
+            .copied()
+            .unwrap_or(GlobalTransform::IDENTITY)
+    }
+}
 
-    let camera_transform = q_transform.get(camera_entity).unwrap();
+/// Local Transforms of the Gizmo root and the selected Entity, captured to restore them later.
+#[derive(Clone, Copy, Debug)]
+pub struct TransformSnapshot {
+    pub gizmo: (Entity, Transform),
+    pub selection: Option<(Entity, Transform)>,
+    /// Global Transform of the parent of the selection, which its Transform is relative to.
+    pub selection_parent: GlobalTransform,
+}
 
-    let direction = gizmo_transform.up();
-    let direction_plane = gizmo_transform.forward();
+impl TransformSnapshot {
+    pub fn capture(
+        q_local_transform: &Query<&mut Transform>,
+        parents: &ParentTransforms,
+        gizmo: Entity,
+        selection: Option<Entity>,
+    ) -> Option<Self> {
+        let gizmo_transform = *q_local_transform.get(gizmo).ok()?;
+        let selection = selection.and_then(|entity| {
+            q_local_transform
+                .get(entity)
+                .ok()
+                .map(|transform| (entity, *transform))
+        });
+        Some(Self {
+            gizmo: (gizmo, gizmo_transform),
+            selection,
+            selection_parent: selection
+                .map_or(GlobalTransform::IDENTITY, |(entity, _)| parents.get(entity)),
+        })
+    }
 
-    let Some(cursor_position) = windows.cursor_position() else {
-        return;
-    };
+    /// Returns the world space rotation of the selection, that of the Gizmo without one.
+    pub fn selection_rotation(&self) -> Quat {
+        self.selection
+            .map_or(self.gizmo.1.rotation, |(_, sel_transform)| {
+                self.selection_parent.rotation() * sel_transform.rotation
+            })
+    }
 
-    // Calculate a ray pointing from the camera into the world based on the cursor's position.
-    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
-        return;
-    };
+    pub fn restore(&self, q_local_transform: &mut Query<&mut Transform>) {
+        for (entity, transform) in std::iter::once(self.gizmo).chain(self.selection) {
+            if let Ok(mut local_transform) = q_local_transform.get_mut(entity) {
+                *local_transform = transform;
+            }
+        }
+    }
+}
 
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance) = ray.intersect_plane(
-        gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
-    ) else {
-        return;
-    };
+/// Constraint of the Gizmo handle being dragged, in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DragConstraint {
+    Axis(Vec3),
+    Plane(Vec3, Vec3),
+    Rotation(Vec3),
+    /// The axis, and the local axis of the selection it scales along.
+    Scale(Vec3, Vec3),
+}
 
-    let point = ray.get_point(distance);
+/// The drag on a Gizmo handle that is currently in progress.
+#[derive(Clone, Debug)]
+pub struct ActiveDrag {
+    pub handle: Entity,
+    pub start: TransformSnapshot,
+    /// Constraint of the handle, known after the first drag event.
+    pub constraint: Option<DragConstraint>,
+    /// World space point on the plane of the handle where the drag started.
+    pub start_point: Option<Vec3>,
+    /// World space offset dragged with the pointer so far.
+    pub translation: Vec3,
+    /// Rotation dragged with the pointer so far.
+    pub rotation: Quat,
+    /// Factor scaled along the axis of a scale handle so far.
+    pub scale: f32,
+    /// Result found by snapping, overriding the pointer. See [`GizmoSnapping`].
+    pub snapped: Option<(Vec3, Quat)>,
+    /// World space point of another Entity the selection was snapped onto.
+    pub snap_target: Option<Vec3>,
+    /// World space segments from the selection to the Entities it is aligned with.
+    pub alignment_guides: Vec<(Vec3, Vec3)>,
+    /// World axes along which the selection is aligned, which it leaves only beyond the release
+    /// distance of the [`GizmoSnapping`].
+    pub aligned_axes: BVec3,
+    /// Number typed during the drag, overriding the pointer along the constraint.
+    pub input: NumericInput,
+    /// Steps the dragged values snap to, resolved from the [`GizmoOptions`] of the selection.
+    pub increments: GizmoIncrements,
+    /// Set if the drag moves only the Gizmo to edit the pivot of the selection, see
+    /// [`GizmoPivotOffset`].
+    pub pivot_edit: bool,
+    /// Set once the drag was aborted, the remaining drag events of it are ignored.
+    pub cancelled: bool,
+}
 
-    // Get the Point before the Drag
-    let Ok(ray_delta) = camera.viewport_to_world(camera_transform, cursor_position - drag.delta)
-    else {
-        return;
-    };
+impl ActiveDrag {
+    /// Returns the translation and rotation to apply on top of the drag-start Transforms.
+    pub fn result(&self) -> (Vec3, Quat) {
+        let (Some(constraint), Some(values)) = (self.constraint, self.input.values()) else {
+            return self.snapped.unwrap_or_else(|| self.dragged());
+        };
+        match constraint {
+            DragConstraint::Axis(axis) => (axis * values[0], Quat::IDENTITY),
+            DragConstraint::Plane(axis_1, axis_2) => (
+                axis_1 * values[0] + axis_2 * values.get(1).copied().unwrap_or_default(),
+                Quat::IDENTITY,
+            ),
+            DragConstraint::Rotation(axis) => (
+                Vec3::ZERO,
+                Quat::from_axis_angle(axis, values[0].to_radians()),
+            ),
+            DragConstraint::Scale(..) => (Vec3::ZERO, Quat::IDENTITY),
+        }
+    }
 
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance_delta) = ray_delta.intersect_plane(
-        gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
-    ) else {
-        return;
-    };
-    let point_delta = ray_delta.get_point(distance_delta);
+    /// Returns the factor to scale the selection by along its local axes.
+    pub fn scale_result(&self) -> Vec3 {
+        let Some(DragConstraint::Scale(_, local_axis)) = self.constraint else {
+            return Vec3::ONE;
+        };
+        let factor = self
+            .input
+            .value()
+            .unwrap_or_else(|| self.increments.snap_scale(self.scale));
+        Vec3::ONE + local_axis.abs() * (factor - 1.)
+    }
 
-    // Calculate the drag in the correct direction
-    let delta_vector = point - point_delta;
-    // Calculate the Effect of the mouse movement in the direction of the Handle
-    let result = delta_vector.project_onto(*direction);
+    /// Returns the translation and rotation dragged with the pointer, snapped to the increments.
+    fn dragged(&self) -> (Vec3, Quat) {
+        let increments = &self.increments;
+        match self.constraint {
+            Some(DragConstraint::Axis(axis)) => (
+                increments.snap_translation(self.translation, &[axis.normalize_or_zero()]),
+                self.rotation,
+            ),
+            Some(DragConstraint::Plane(axis_1, axis_2)) => (
+                increments.snap_translation(
+                    self.translation,
+                    &[axis_1.normalize_or_zero(), axis_2.normalize_or_zero()],
+                ),
+                self.rotation,
+            ),
+            Some(DragConstraint::Rotation(axis)) => (
+                self.translation,
+                increments.snap_rotation(self.rotation, axis),
+            ),
+            Some(DragConstraint::Scale(..)) | None => (self.translation, self.rotation),
+        }
+    }
+}
 
-    // Set the transformation
-    let mut parent_transform_local = q_local_transform.get_mut(parent_entity).unwrap();
-    parent_transform_local.translation += result;
+#[derive(Resource, Default)]
+pub struct GizmoDrag {
+    pub active: Option<ActiveDrag>,
+}
 
-    // Set the Transformation to the connected Object
-    if let Some(sel_entity) = selection.entity {
-        let mut selection_transform_local = q_local_transform.get_mut(sel_entity).unwrap();
-        selection_transform_local.translation += result;
+impl GizmoDrag {
+    pub fn is_cancelled(&self) -> bool {
+        self.active.as_ref().is_some_and(|drag| drag.cancelled)
     }
 }
 
-/// This Observer Function allows to move in the two directions on the Plane created from Forward and Right of the dragged Entity  
-pub fn transform_plane(
-    drag: Trigger<Pointer<Drag>>,
+/// Sent when a drag was cancelled and the Transforms were restored to their drag-start values.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct GizmoDragCancelled {
+    pub handle: Entity,
+    pub selection: Option<Entity>,
+}
+
+/// This Observer Function records the Transforms at the start of a drag on a Gizmo handle that
+/// transforms the selection
+#[allow(clippy::too_many_arguments)]
+pub fn drag_start(
+    mut drag: Trigger<Pointer<DragStart>>,
+    q_gizmo_parts: Query<(), (With<TransformGizmoPart>, Without<GizmoHandleAction>)>,
     q_parents: Query<&ChildOf>,
-    q_transform: Query<&GlobalTransform>,
-    mut q_local_transform: Query<&mut Transform>,
-    windows: Single<&Window>,
-    q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
+    q_local_transform: Query<&mut Transform>,
+    parents: ParentTransforms,
+    keys: Res<ButtonInput<KeyCode>>,
     selection: Res<TransformGizmoResource>,
+    q_instances: Query<&TransformGizmoInstance>,
+    q_options: Query<&GizmoOptions>,
+    mut drag_state: ResMut<GizmoDrag>,
 ) {
-    // Check if the correct Mouse Button is pressed
-    if drag.button != selection.drag_button {
+    let handle_entity = drag.target();
+    if drag.button != selection.drag_button || !q_gizmo_parts.contains(handle_entity) {
         return;
     }
+    drag.propagate(false);
 
-    let (camera_entity, camera) = *q_camera;
-
-    let handle_entity = drag.target();
-
-    let parent_entity = q_parents.get(handle_entity).unwrap().parent();
-
-    let gizmo_transform = q_transform.get(handle_entity).unwrap();
-
-    let camera_transform = q_transform.get(camera_entity).unwrap();
-
-    let axis_1 = Vec3::from(gizmo_transform.forward());
-    let axis_2 = Vec3::from(gizmo_transform.right());
-
-    let direction_plane = gizmo_transform.up();
-
-    let Some(cursor_position) = windows.cursor_position() else {
+    let Ok(parent) = q_parents.get(handle_entity) else {
         return;
     };
+    let sel_entity = selection.selection_of(parent.parent(), &q_instances);
+    let options = selection.resolve(sel_entity.and_then(|entity| q_options.get(entity).ok()));
+    drag_state.active =
+        TransformSnapshot::capture(&q_local_transform, &parents, parent.parent(), sel_entity).map(
+            |start| ActiveDrag {
+                handle: handle_entity,
+                start,
+                constraint: None,
+                start_point: None,
+                translation: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+                scale: 1.,
+                snapped: None,
+                snap_target: None,
+                alignment_guides: Vec::new(),
+                aligned_axes: BVec3::FALSE,
+                input: NumericInput::default(),
+                increments: options.increments,
+                pivot_edit: keys.pressed(selection.pivot_edit_modifier),
+                cancelled: false,
+            },
+        );
+}
 
-    // Calculate a ray pointing from the camera into the world based on the cursor's position.
-    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
+/// This Observer Function clears the drag state once a drag on a Gizmo handle ends, and stores
+/// the new pivot of the selection after a pivot edit
+pub fn drag_end(
+    mut drag: Trigger<Pointer<DragEnd>>,
+    mut commands: Commands,
+    mut drag_state: ResMut<GizmoDrag>,
+    q_transform: Query<&GlobalTransform>,
+    q_local_transform: Query<&Transform>,
+) {
+    let Some(active) = drag_state
+        .active
+        .take_if(|active| active.handle == drag.target())
+    else {
         return;
     };
+    drag.propagate(false);
 
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance) = ray.intersect_plane(
-        gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
-    ) else {
+    if !active.pivot_edit || active.cancelled {
         return;
-    };
-
-    let point = ray.get_point(distance);
+    }
+    let (gizmo, _) = active.start.gizmo;
+    if let (Some((sel_entity, _)), Ok(gizmo_transform)) =
+        (active.start.selection, q_local_transform.get(gizmo))
+    {
+        if let Ok(sel_transform) = q_transform.get(sel_entity) {
+            let offset = sel_transform
+                .affine()
+                .inverse()
+                .transform_point3(gizmo_transform.translation);
+            commands.entity(sel_entity).insert(GizmoPivotOffset(offset));
+        }
+    }
+}
 
-    // Get the Point before the Drag
-    let Ok(ray_delta) = camera.viewport_to_world(camera_transform, cursor_position - drag.delta)
-    else {
+/// Aborts the drag in progress when `Escape` or the right mouse button is pressed, restoring the
+/// selected Entity and the Gizmo root to their drag-start Transforms.
+pub fn cancel_drag(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut drag_state: ResMut<GizmoDrag>,
+    mut q_local_transform: Query<&mut Transform>,
+    mut cancelled: EventWriter<GizmoDragCancelled>,
+) {
+    let Some(active) = drag_state.active.as_mut() else {
         return;
     };
-
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance_delta) = ray_delta.intersect_plane(
-        gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
-    ) else {
+    if active.cancelled
+        || !(keys.just_pressed(KeyCode::Escape) || mouse_input.just_pressed(MouseButton::Right))
+    {
         return;
-    };
-    let point_delta = ray_delta.get_point(distance_delta);
-
-    // Calculate the drag in the correct direction
-    let delta_vector = point - point_delta;
-    // Calculate the Effect of the mouse movement in the direction of the Handle
-    let result = delta_vector.project_onto(axis_1) + delta_vector.project_onto(axis_2);
+    }
 
-    // Set the transformation
-    let mut parent_transform_local = q_local_transform.get_mut(parent_entity).unwrap();
-    parent_transform_local.translation += result;
+    active.start.restore(&mut q_local_transform);
+    active.cancelled = true;
+    cancelled.write(GizmoDragCancelled {
+        handle: active.handle,
+        selection: active.start.selection.map(|(entity, _)| entity),
+    });
+}
 
-    // Set the Transformation to the connected Object
-    if let Some(sel_entity) = selection.entity {
-        let mut selection_transform_local = q_local_transform.get_mut(sel_entity).unwrap();
-        selection_transform_local.translation += result;
+/// Lets the user type an exact value for the drag in progress, see [`NumericInput`].
+pub fn drag_numeric_input(keys: Res<ButtonInput<KeyCode>>, mut drag_state: ResMut<GizmoDrag>) {
+    if drag_state.is_cancelled() || keys.get_just_pressed().len() == 0 {
+        return;
+    }
+    if let Some(active) = drag_state.active.as_mut() {
+        active.input.update(&keys);
     }
 }
 
-/// This Observer Function allows to move in the two directions on the Plane created from the Camera View of the dragged Entity
-pub fn transform_camera_plane(
-    drag: Trigger<Pointer<Drag>>,
-    q_parents: Query<&ChildOf>,
-    q_transform: Query<&GlobalTransform>,
+/// Applies the drag in progress on top of the drag-start Transforms of the Gizmo and the selection,
+/// within the [`GizmoConstraints`] of the selection.
+pub fn apply_drag(
+    drag_state: Res<GizmoDrag>,
     mut q_local_transform: Query<&mut Transform>,
-    windows: Single<&Window>,
-    q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
-    selection: Res<TransformGizmoResource>,
+    q_constraints: Query<(&GizmoConstraints, Option<&Aabb>)>,
 ) {
-    // Check if the correct Mouse Button is pressed
-    if drag.button != selection.drag_button {
+    if !drag_state.is_changed() || drag_state.is_cancelled() {
         return;
     }
-    let (camera_entity, camera) = *q_camera;
-
-    let handle_entity = drag.target();
-
-    let parent_entity = q_parents.get(handle_entity).unwrap().parent();
-
-    let gizmo_transform = q_transform.get(handle_entity).unwrap();
-
-    let camera_transform = q_transform.get(camera_entity).unwrap();
-
-    let Some(cursor_position) = windows.cursor_position() else {
+    let Some(active) = drag_state.active.as_ref() else {
         return;
     };
 
-    // Calculate a ray pointing from the camera into the world based on the cursor's position.
-    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
-        return;
-    };
+    // Always start from the drag-start Transforms, so typed values replace the dragged ones
+    active.start.restore(&mut q_local_transform);
 
-    let direction_plane = camera_transform.back();
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance) = ray.intersect_plane(
-        gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
-    ) else {
+    let (gizmo, _) = active.start.gizmo;
+    let (translation, rotation) = active.result();
+    if active.pivot_edit {
+        // Only the Gizmo moves, rotating the pivot has no meaning
+        apply_translation(&mut q_local_transform, gizmo, None, translation);
         return;
+    }
+    let selection = active
+        .start
+        .selection
+        .map(|(entity, _)| (entity, &active.start.selection_parent));
+    let constraints = active
+        .start
+        .selection
+        .and_then(|(entity, sel_start)| Some((q_constraints.get(entity).ok()?.0, sel_start)));
+    let (translation, rotation) =
+        constraints.map_or((translation, rotation), |(constraints, sel_start)| {
+            constraints.constrain(
+                &active.start.selection_parent,
+                &sel_start,
+                translation,
+                rotation,
+            )
+        });
+    apply_translation(&mut q_local_transform, gizmo, selection, translation);
+    apply_rotation(&mut q_local_transform, gizmo, selection, rotation);
+    let scale = active.scale_result();
+    let scale = constraints.map_or(scale, |(constraints, _)| constraints.constrain_scale(scale));
+    apply_scale(&mut q_local_transform, gizmo, selection, scale);
+    clamp_selection(&mut q_local_transform, &q_constraints, gizmo, selection);
+}
+
+/// Returns the angle of `rotation` around `axis`, in the range `-PI..=PI`.
+pub fn signed_angle(rotation: Quat, axis: Vec3) -> f32 {
+    let (rotation_axis, angle) = rotation.to_axis_angle();
+    // Quaternions may take the long way around, use the shortest angle
+    let angle = if angle > std::f32::consts::PI {
+        angle - std::f32::consts::TAU
+    } else {
+        angle
     };
+    angle * rotation_axis.dot(axis).signum()
+}
 
-    let point = ray.get_point(distance);
+/// Moves the Gizmo root and the selected Entity by a world space offset. The selection comes with
+/// the Global Transform of its parent.
+pub fn apply_translation(
+    q_local_transform: &mut Query<&mut Transform>,
+    gizmo: Entity,
+    selection: Option<(Entity, &GlobalTransform)>,
+    offset: Vec3,
+) {
+    if let Ok(mut gizmo_transform) = q_local_transform.get_mut(gizmo) {
+        gizmo_transform.translation += offset;
+    }
+    if let Some((entity, parent)) = selection {
+        if let Ok(mut local_transform) = q_local_transform.get_mut(entity) {
+            local_transform.translation += parent.affine().inverse().transform_vector3(offset);
+        }
+    }
+}
 
-    // Get the Point before the Drag
-    let Ok(ray_delta) = camera.viewport_to_world(camera_transform, cursor_position - drag.delta)
-    else {
+/// Rotates the Gizmo root and the selected Entity around the Gizmo root, which sits on the pivot.
+/// The selection comes with the Global Transform of its parent.
+pub fn apply_rotation(
+    q_local_transform: &mut Query<&mut Transform>,
+    gizmo: Entity,
+    selection: Option<(Entity, &GlobalTransform)>,
+    rotation: Quat,
+) {
+    let Ok(mut gizmo_transform) = q_local_transform.get_mut(gizmo) else {
         return;
     };
+    let pivot = gizmo_transform.translation;
+    gizmo_transform.rotate_around(pivot, rotation);
+    if let Some((entity, parent)) = selection {
+        if let Ok(mut local_transform) = q_local_transform.get_mut(entity) {
+            // The pivot and the rotation in the space of the parent
+            let parent_rotation = parent.rotation();
+            local_transform.rotate_around(
+                parent.affine().inverse().transform_point3(pivot),
+                parent_rotation.inverse() * rotation * parent_rotation,
+            );
+        }
+    }
+}
 
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance_delta) = ray_delta.intersect_plane(
-        gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
-    ) else {
+/// Scales the selected Entity along its local axes around the Gizmo root, which sits on the
+/// pivot. The Gizmo keeps its size. The selection comes with the Global Transform of its parent.
+pub fn apply_scale(
+    q_local_transform: &mut Query<&mut Transform>,
+    gizmo: Entity,
+    selection: Option<(Entity, &GlobalTransform)>,
+    scale: Vec3,
+) {
+    let Ok(pivot) = q_local_transform.get(gizmo).map(|gizmo| gizmo.translation) else {
         return;
     };
-    let point_delta = ray_delta.get_point(distance_delta);
-
-    // Calculate the drag in the correct direction
-
-    let delta_vector = point - point_delta;
-
-    let axis_1 = Vec3::from(camera_transform.up());
-    let axis_2 = Vec3::from(camera_transform.right());
-
-    // Calculate the Effect of the mouse movement in the direction of the Handle
-    let result = delta_vector.project_onto(axis_1) + delta_vector.project_onto(axis_2);
-
-    // Set the transformation
-    let mut parent_transform_local = q_local_transform.get_mut(parent_entity).unwrap();
-    parent_transform_local.translation += result;
-
-    // Set the Transformation to the connected Object
-    if let Some(sel_entity) = selection.entity {
-        let mut selection_transform_local = q_local_transform.get_mut(sel_entity).unwrap();
-        selection_transform_local.translation += result;
+    if let Some((mut local_transform, parent)) = selection
+        .and_then(|(entity, parent)| Some((q_local_transform.get_mut(entity).ok()?, parent)))
+    {
+        let pivot = parent.affine().inverse().transform_point3(pivot);
+        let offset = local_transform.rotation.inverse() * (local_transform.translation - pivot);
+        local_transform.translation = pivot + local_transform.rotation * (offset * scale);
+        local_transform.scale *= scale;
     }
 }
 
-/// This Observer Function allows to rotate the dragged Entity
-pub fn transform_rotation(
+/// Intersects the rays through the cursor after and before a drag event with a plane. Both points
+/// are relative to the camera, which keeps them precise far from the world origin.
+pub fn camera_relative_drag(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    cursor_position: Vec2,
+    delta: Vec2,
+    plane_origin: Vec3,
+    plane_normal: Dir3,
+) -> Option<(Vec3, Vec3)> {
+    let camera_at_origin = GlobalTransform::from(
+        camera_transform
+            .compute_transform()
+            .with_translation(Vec3::ZERO),
+    );
+    let plane_origin = plane_origin - camera_transform.translation();
+    let intersect = |position| {
+        let ray = camera.viewport_to_world(&camera_at_origin, position).ok()?;
+        let distance = ray.intersect_plane(plane_origin, InfinitePlane3d::new(plane_normal))?;
+        Some(ray.get_point(distance))
+    };
+    Some((
+        intersect(cursor_position)?,
+        intersect(cursor_position - delta)?,
+    ))
+}
+
+/// This Observer Function moves, rotates or scales the selection while a [`GizmoHandle`] is
+/// dragged, as its kind describes, or triggers [`GizmoHandleDrag`] for handles with a
+/// [`GizmoHandleAction`]
+#[allow(clippy::too_many_arguments)]
+pub fn drag_handle(
     drag: Trigger<Pointer<Drag>>,
-    q_parents: Query<&ChildOf>,
+    mut commands: Commands,
+    q_handles: Query<(&GizmoHandle, &ChildOf, Has<GizmoHandleAction>)>,
+    q_instances: Query<&TransformGizmoInstance>,
     q_transform: Query<&GlobalTransform>,
-    mut q_local_transform: Query<&mut Transform>,
     windows: Single<&Window>,
     q_camera: Single<(Entity, &Camera), With<GizmoPickSource>>,
     selection: Res<TransformGizmoResource>,
+    blocked: Res<GizmoInputBlocked>,
+    mut drag_state: ResMut<GizmoDrag>,
 ) {
-    // Check if the correct Mouse Button is pressed
-    if drag.button != selection.drag_button {
+    let Ok((handle, parent, has_action)) = q_handles.get(drag.target()) else {
+        return;
+    };
+    // Check if the correct Mouse Button is pressed, input isn't blocked and the drag wasn't cancelled
+    if drag.button != selection.drag_button || blocked.0 || drag_state.is_cancelled() {
         return;
     }
-
     let (camera_entity, camera) = *q_camera;
 
-    let handle_entity = drag.target();
-
-    let parent_entity = q_parents.get(handle_entity).unwrap().parent();
-
-    let gizmo_transform = q_transform.get(handle_entity).unwrap();
-
-    let camera_transform = q_transform.get(camera_entity).unwrap();
-
-    let axis_1 = Vec3::from(gizmo_transform.up());
-
-    let direction_plane = gizmo_transform.up();
-
-    let Some(cursor_position) = windows.cursor_position() else {
+    let (Ok(gizmo_transform), Ok(camera_transform)) = (
+        q_transform.get(parent.parent()),
+        q_transform.get(camera_entity),
+    ) else {
         return;
     };
+    let gizmo_rotation = gizmo_transform.rotation();
+    let view_axis = camera_transform.back();
+
+    // Plane the cursor is projected on
+    let direction_plane = match *handle {
+        GizmoHandle::Axis(axis) | GizmoHandle::Scale(axis) => {
+            // The plane containing the axis that faces the camera the most
+            let axis = gizmo_rotation * axis;
+            Dir3::new(axis.cross(*view_axis).cross(axis)).unwrap_or(view_axis)
+        }
+        GizmoHandle::Plane(normal) | GizmoHandle::Ring(normal) => {
+            Dir3::new(gizmo_rotation * normal).unwrap_or(view_axis)
+        }
+        GizmoHandle::ViewPlane => view_axis,
+    };
 
-    // Calculate a ray pointing from the camera into the world based on the cursor's position.
-    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
+    let Some(cursor_position) = windows.cursor_position() else {
         return;
     };
 
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance) = ray.intersect_plane(
+    // Intersect relative to the camera, so the points stay precise far from the world origin
+    let Some((point, point_delta)) = camera_relative_drag(
+        camera,
+        camera_transform,
+        cursor_position,
+        drag.delta,
         gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
+        direction_plane,
     ) else {
         return;
     };
+    let origin = gizmo_transform.translation() - camera_transform.translation();
+    // Calculate the drag in the correct direction
+    let delta_vector = point - point_delta;
 
-    let point = ray.get_point(distance);
+    // Translation, rotation and scale factor of this drag event
+    let (constraint, translation, rotation, scale) = match *handle {
+        GizmoHandle::Axis(axis) => {
+            let axis = gizmo_rotation * axis;
+            let translation = delta_vector.project_onto(axis);
+            (DragConstraint::Axis(axis), translation, Quat::IDENTITY, 1.)
+        }
+        GizmoHandle::Plane(normal) => {
+            let (axis_1, axis_2) = GizmoHandle::plane_axes(normal);
+            let (axis_1, axis_2) = (gizmo_rotation * axis_1, gizmo_rotation * axis_2);
+            let translation = delta_vector.project_onto(axis_1) + delta_vector.project_onto(axis_2);
+            (
+                DragConstraint::Plane(axis_1, axis_2),
+                translation,
+                Quat::IDENTITY,
+                1.,
+            )
+        }
+        GizmoHandle::ViewPlane => {
+            let axis_1 = Vec3::from(camera_transform.right());
+            let axis_2 = Vec3::from(camera_transform.up());
+            let translation = delta_vector.project_onto(axis_1) + delta_vector.project_onto(axis_2);
+            (
+                DragConstraint::Plane(axis_1, axis_2),
+                translation,
+                Quat::IDENTITY,
+                1.,
+            )
+        }
+        GizmoHandle::Ring(axis) => {
+            let axis = gizmo_rotation * axis;
+            let (from, to) = (point_delta - origin, point - origin);
+            let angle = from.cross(to).dot(axis).atan2(from.dot(to));
+            (
+                DragConstraint::Rotation(axis),
+                Vec3::ZERO,
+                Quat::from_axis_angle(axis, angle),
+                1.,
+            )
+        }
+        GizmoHandle::Scale(gizmo_axis) => {
+            let axis = gizmo_rotation * gizmo_axis;
+            // The Gizmo isn't aligned with the selection in World space, scale along the axis of
+            // the selection that matches the handle instead
+            let selection_rotation = selection
+                .selection_of(parent.parent(), &q_instances)
+                .and_then(|entity| q_transform.get(entity).ok())
+                .map_or(gizmo_rotation, GlobalTransform::rotation);
+            let local_axis = selection_rotation.inverse() * axis;
+            // Ratio of the distances of the cursor from the pivot along the axis
+            let (from, to) = ((point_delta - origin).dot(axis), (point - origin).dot(axis));
+            let scale = if from.abs() > f32::EPSILON {
+                to / from
+            } else {
+                1.
+            };
+            (
+                DragConstraint::Scale(axis, local_axis),
+                Vec3::ZERO,
+                Quat::IDENTITY,
+                scale,
+            )
+        }
+    };
 
-    // Get the Point before the Drag
-    let Ok(ray_delta) = camera.viewport_to_world(camera_transform, cursor_position - drag.delta)
-    else {
+    if has_action {
+        commands.trigger_targets(
+            GizmoHandleDrag {
+                selection: selection.selection_of(parent.parent(), &q_instances),
+                constraint,
+                translation,
+                rotation,
+                scale,
+            },
+            drag.target(),
+        );
         return;
-    };
+    }
 
-    // Calculate if and where the ray is hitting the Handle plane.
-    let Some(distance_delta) = ray_delta.intersect_plane(
-        gizmo_transform.translation(),
-        InfinitePlane3d::new(direction_plane),
-    ) else {
+    // Accumulate the drag, `apply_drag` applies it on top of the drag-start Transforms
+    let Some(active) = drag_state.active.as_mut() else {
         return;
     };
-    let point_delta = ray_delta.get_point(distance_delta);
-
-    // Calculate the drag in the correct direction
-    // Calculate the Effect of the mouse movement in the direction of the Handle
-    let origin = gizmo_transform.translation();
-    let origin_dir = gizmo_transform.back();
+    active
+        .start_point
+        .get_or_insert(point_delta + camera_transform.translation());
+    active.constraint = Some(constraint);
+    active.translation += translation;
+    active.rotation = rotation * active.rotation;
+    active.scale *= scale;
+}
 
-    let dir1 = (point - origin).normalize();
-    let dir2 = (point_delta - origin).normalize();
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    /// Returns the world positions of a child after its parent was rotated and scaled.
+    fn transform_parented(
+        operation: impl Fn(&mut Query<&mut Transform>, Entity, Option<(Entity, &GlobalTransform)>)
+            + Send
+            + Sync
+            + 'static,
+    ) -> Vec3 {
+        let mut world = World::new();
+        let parent = GlobalTransform::from(
+            Transform::from_xyz(5., 0., 0.)
+                .with_rotation(Quat::from_rotation_y(1.))
+                .with_scale(Vec3::splat(2.)),
+        );
+        let child = world.spawn(Transform::from_xyz(1., 0., 0.)).id();
+        let child_position = parent.transform_point(Vec3::new(1., 0., 0.));
+        // The Gizmo sits on the child
+        let gizmo = world
+            .spawn(Transform::from_translation(child_position))
+            .id();
+        world
+            .run_system_once(move |mut q_local_transform: Query<&mut Transform>| {
+                operation(&mut q_local_transform, gizmo, Some((child, &parent)));
+            })
+            .unwrap();
+        let local = *world.get::<Transform>(child).unwrap();
+        parent.transform_point(local.translation)
+    }
 
-    let angle_side = origin_dir.angle_between(dir1);
-    let angle_side_2 = origin_dir.angle_between(dir2);
+    #[test]
+    fn parented_selection_rotates_around_the_pivot() {
+        // Rotating around the origin of the selection keeps it in place
+        let before = transform_parented(|_, _, _| {});
+        let after = transform_parented(|q, gizmo, selection| {
+            apply_rotation(q, gizmo, selection, Quat::from_rotation_z(1.));
+        });
+        assert!(after.abs_diff_eq(before, 1e-5));
+    }
 
-    let angle_diff = angle_side - angle_side_2;
+    #[test]
+    fn parented_selection_moves_by_the_world_offset() {
+        let before = transform_parented(|_, _, _| {});
+        let after = transform_parented(|q, gizmo, selection| {
+            apply_translation(q, gizmo, selection, Vec3::Y);
+            apply_scale(q, gizmo, selection, Vec3::splat(3.));
+        });
+        assert!(after.abs_diff_eq(before + Vec3::Y, 1e-5));
+    }
 
-    // Set the transformation
-    let mut parent_transform_local = q_local_transform.get_mut(parent_entity).unwrap();
-    parent_transform_local.rotate(Quat::from_axis_angle(axis_1, angle_diff));
+    #[test]
+    fn signed_angle_follows_the_axis() {
+        let rotation = Quat::from_axis_angle(Vec3::Y, 0.5);
+        assert!((signed_angle(rotation, Vec3::Y) - 0.5).abs() < 1e-5);
+        assert!((signed_angle(rotation, Vec3::NEG_Y) + 0.5).abs() < 1e-5);
+    }
 
-    // Set the Transformation to the connected Object
-    if let Some(sel_entity) = selection.entity {
-        let mut selection_transform_local = q_local_transform.get_mut(sel_entity).unwrap();
-        selection_transform_local.rotate(Quat::from_axis_angle(axis_1, angle_diff));
+    #[test]
+    fn signed_angle_takes_the_short_way() {
+        let rotation = Quat::from_axis_angle(Vec3::Z, 270f32.to_radians());
+        assert!((signed_angle(rotation, Vec3::Z) + 90f32.to_radians()).abs() < 1e-4);
     }
 }
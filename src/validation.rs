@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use crate::*;
+
+/// Triggered on the selected Entity whenever a drag or a modal operation is about to change its
+/// Transform, after [`GizmoConstraints`] were applied. Observers can accept the change by doing
+/// nothing, clamp it by editing [`Self::transform`] or [`Self::reject`] it, which keeps the last
+/// accepted Transform. The Gizmo root follows the final result. All Transforms are local, relative
+/// to the parent of the Entity.
+#[derive(Event, Clone, Debug)]
+pub struct ValidateGizmoTransform {
+    /// The Entity that is transformed.
+    pub entity: Entity,
+    /// Transform of the Entity before the drag or modal operation.
+    pub start: Transform,
+    /// Transform the Gizmo wants to apply.
+    pub proposed: Transform,
+    /// Transform that will be applied, starts out as [`Self::proposed`].
+    pub transform: Transform,
+    rejected: bool,
+}
+
+impl ValidateGizmoTransform {
+    /// Rejects the change, keeping the last accepted Transform.
+    pub fn reject(&mut self) {
+        self.rejected = true;
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        self.rejected
+    }
+}
+
+/// Lets observers of [`ValidateGizmoTransform`] accept, clamp or reject the Transform the drag or
+/// modal operation in progress proposes for the selection.
+pub fn validate_transform(world: &mut World, mut last_valid: Local<Option<(Entity, Transform)>>) {
+    let start = {
+        let drag_state = world.resource::<GizmoDrag>();
+        let modal = world.resource::<GizmoModal>();
+        match (drag_state.active.as_ref(), modal.active.as_ref()) {
+            (Some(active), _) if !active.cancelled && !active.pivot_edit => Some(active.start),
+            (None, Some(active)) => Some(active.start),
+            _ => None,
+        }
+    };
+    let Some((gizmo, (sel_entity, sel_start), parent)) =
+        start.and_then(|start| Some((start.gizmo.0, start.selection?, start.selection_parent)))
+    else {
+        *last_valid = None;
+        return;
+    };
+    let Some(proposed) = world.get::<Transform>(sel_entity).copied() else {
+        return;
+    };
+    let previous = match *last_valid {
+        Some((entity, transform)) if entity == sel_entity => transform,
+        _ => sel_start,
+    };
+    if proposed == previous {
+        return;
+    }
+
+    let mut validation = ValidateGizmoTransform {
+        entity: sel_entity,
+        start: sel_start,
+        proposed,
+        transform: proposed,
+        rejected: false,
+    };
+    world.trigger_targets_ref(&mut validation, sel_entity);
+    let accepted = if validation.rejected {
+        previous
+    } else {
+        validation.transform
+    };
+    *last_valid = Some((sel_entity, accepted));
+    if accepted == proposed {
+        return;
+    }
+
+    if let Some(mut sel_transform) = world.get_mut::<Transform>(sel_entity) {
+        *sel_transform = accepted;
+    }
+    if let Some(mut gizmo_transform) = world.get_mut::<Transform>(gizmo) {
+        follow_selection(&mut gizmo_transform, &parent, &proposed, &accepted);
+    }
+}
+
+/// Keeps the Gizmo on the same point of the selection, and aligned with it, when the local
+/// Transform of the selection relative to `parent` changes from `proposed` to `accepted`.
+fn follow_selection(
+    gizmo_transform: &mut Transform,
+    parent: &GlobalTransform,
+    proposed: &Transform,
+    accepted: &Transform,
+) {
+    // The Gizmo root is in world space
+    let proposed = parent.mul_transform(*proposed).compute_transform();
+    let accepted = parent.mul_transform(*accepted).compute_transform();
+    let offset = proposed.rotation.inverse() * (gizmo_transform.translation - proposed.translation);
+    gizmo_transform.translation = accepted.translation + accepted.rotation * offset;
+    gizmo_transform.rotation =
+        accepted.rotation * proposed.rotation.inverse() * gizmo_transform.rotation;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn gizmo_follows_a_clamped_parented_selection() {
+        let parent = GlobalTransform::from(
+            Transform::from_xyz(5., 0., 0.).with_rotation(Quat::from_rotation_y(FRAC_PI_2)),
+        );
+        let proposed = Transform::from_xyz(2., 0., 0.);
+        let accepted =
+            Transform::from_xyz(1., 0., 0.).with_rotation(Quat::from_rotation_x(FRAC_PI_2));
+        // The Gizmo sits on the origin of the selection, aligned with it
+        let mut gizmo_transform = parent.mul_transform(proposed).compute_transform();
+        follow_selection(&mut gizmo_transform, &parent, &proposed, &accepted);
+
+        let expected = parent.mul_transform(accepted).compute_transform();
+        assert!(gizmo_transform
+            .translation
+            .abs_diff_eq(expected.translation, 1e-5));
+        assert!(gizmo_transform
+            .rotation
+            .abs_diff_eq(expected.rotation, 1e-5));
+        // Local +X of the parent points along world -Z
+        assert!(gizmo_transform
+            .translation
+            .abs_diff_eq(Vec3::new(5., 0., -1.), 1e-5));
+    }
+}
@@ -0,0 +1,253 @@
+use bevy::{prelude::*, render::view::RenderLayers};
+
+use crate::*;
+
+/// Gizmo config group of the lines drawn during drags. They are rendered by the
+/// [`InternalGizmoCamera`], on top of the scene like the Gizmo itself.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct TransformGizmoLines;
+
+impl TransformGizmoLines {
+    pub fn config(render_layers: RenderLayers) -> GizmoConfig {
+        GizmoConfig {
+            render_layers,
+            ..default()
+        }
+    }
+}
+
+/// Marker of the filled pie segment that shows the swept angle of rotation drags.
+#[derive(Component)]
+pub struct SweptAnglePie;
+
+/// Length of the constraint axis lines in each direction, long enough to look infinite.
+const AXIS_LINE_LENGTH: f32 = 10_000.0;
+
+/// Radius of the rotation arcs of the Gizmo, in the local space of the Gizmo root.
+const ARC_RADIUS: f32 = 1.0;
+
+/// Length of the dashes and of the gaps between them of alignment guides, relative to the scale of
+/// the Gizmo.
+const GUIDE_DASH_LENGTH: f32 = 0.1;
+
+/// Most dashes an alignment guide is drawn with.
+const MAX_GUIDE_DASHES: usize = 64;
+
+/// Returns the color of the Gizmo handles for the world axis closest to `axis`.
+pub fn axis_color(axis: Vec3) -> Color {
+    let axis = axis.abs();
+    let hue = if axis.x >= axis.y && axis.x >= axis.z {
+        0.0
+    } else if axis.y >= axis.z {
+        120.0
+    } else {
+        240.0
+    };
+    Color::hsl(hue, 0.8, 0.6)
+}
+
+/// Draws the constraint axis lines of the active drag or modal operation, a trail with a ghost
+/// marker from the start position of a translation to the current one, the snap target and the
+/// alignment guides.
+pub fn draw_drag_guides(
+    mut lines: Gizmos<TransformGizmoLines>,
+    drag_state: Res<GizmoDrag>,
+    modal: Res<GizmoModal>,
+    q_transform: Query<&GlobalTransform>,
+) {
+    let (gizmo, start, axes, translating): (Entity, Vec3, Vec<Vec3>, bool) = if let Some(active) =
+        drag_state
+            .active
+            .as_ref()
+            .filter(|active| !active.cancelled)
+    {
+        let (gizmo, gizmo_start) = active.start.gizmo;
+        let start = gizmo_start.translation;
+        match active.constraint {
+            Some(DragConstraint::Axis(axis)) => (gizmo, start, vec![axis], true),
+            Some(DragConstraint::Plane(axis_1, axis_2)) => {
+                (gizmo, start, vec![axis_1, axis_2], true)
+            }
+            Some(DragConstraint::Rotation(axis) | DragConstraint::Scale(axis, _)) => {
+                (gizmo, start, vec![axis], false)
+            }
+            None => return,
+        }
+    } else if let Some(active) = modal.active.as_ref() {
+        let (gizmo, gizmo_start) = active.start.gizmo;
+        let axes = active
+            .constraint
+            .world_axis(active.start.selection_rotation())
+            .into_iter()
+            .collect();
+        (
+            gizmo,
+            gizmo_start.translation,
+            axes,
+            active.operation == ModalOperation::Translate,
+        )
+    } else {
+        return;
+    };
+
+    for axis in axes {
+        let axis = axis.normalize_or_zero();
+        lines.line(
+            start - axis * AXIS_LINE_LENGTH,
+            start + axis * AXIS_LINE_LENGTH,
+            axis_color(axis),
+        );
+    }
+
+    let Ok(gizmo_transform) = q_transform.get(gizmo) else {
+        return;
+    };
+    if translating {
+        let current = gizmo_transform.translation();
+        let marker_radius = 0.1 * gizmo_transform.compute_transform().scale.x;
+        lines.line(start, current, Color::WHITE);
+        lines.sphere(
+            Isometry3d::from_translation(start),
+            marker_radius,
+            Color::WHITE.with_alpha(0.5),
+        );
+
+        // Highlight the vertex, edge midpoint or bounds corner the selection snapped onto
+        if let Some(target) = drag_state
+            .active
+            .as_ref()
+            .and_then(|active| active.snap_target)
+        {
+            lines.cross(
+                Isometry3d::from_translation(target),
+                marker_radius * 2.0,
+                Color::srgb(1.0, 0.8, 0.0),
+            );
+            lines.sphere(
+                Isometry3d::from_translation(target),
+                marker_radius,
+                Color::srgb(1.0, 0.8, 0.0),
+            );
+        }
+
+        let guides = drag_state
+            .active
+            .as_ref()
+            .map(|active| active.alignment_guides.as_slice())
+            .unwrap_or_default();
+        for (from, to) in guides {
+            dashed_line(
+                &mut lines,
+                *from,
+                *to,
+                GUIDE_DASH_LENGTH * gizmo_transform.compute_transform().scale.x,
+                Color::srgb(1.0, 0.3, 0.8),
+            );
+        }
+    }
+}
+
+/// Draws a line from `start` to `end` made of dashes separated by equally long gaps.
+fn dashed_line(
+    lines: &mut Gizmos<TransformGizmoLines>,
+    start: Vec3,
+    end: Vec3,
+    dash_length: f32,
+    color: Color,
+) {
+    let length = start.distance(end);
+    if length <= f32::EPSILON || dash_length <= f32::EPSILON {
+        return;
+    }
+    // Long guides get longer dashes instead of more of them
+    let dash_length = dash_length.max(length / (2.0 * MAX_GUIDE_DASHES as f32));
+    let direction = (end - start) / length;
+    let mut position = 0.0;
+    while position < length {
+        let dash_end = (position + dash_length).min(length);
+        lines.line(
+            start + direction * position,
+            start + direction * dash_end,
+            color,
+        );
+        position += 2.0 * dash_length;
+    }
+}
+
+/// Shows a filled pie segment from the drag-start angle to the current angle during rotation drags.
+pub fn update_swept_angle_pie(
+    drag_state: Res<GizmoDrag>,
+    q_transform: Query<&GlobalTransform>,
+    q_pie: Single<
+        (
+            &Mesh3d,
+            &MeshMaterial3d<GizmoMaterial>,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<SweptAnglePie>,
+    >,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GizmoMaterial>>,
+) {
+    let (mesh, material, mut transform, mut visibility) = q_pie.into_inner();
+
+    let Some((active, axis, start_point)) = drag_state
+        .active
+        .as_ref()
+        .filter(|active| !active.cancelled)
+        .and_then(|active| match (active.constraint, active.start_point) {
+            (Some(DragConstraint::Rotation(axis)), Some(start_point)) => {
+                Some((active, axis, start_point))
+            }
+            _ => None,
+        })
+    else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+
+    let (gizmo, gizmo_start) = active.start.gizmo;
+    let center = gizmo_start.translation;
+    let Ok(gizmo_transform) = q_transform.get(gizmo) else {
+        return;
+    };
+    let Ok(axis) = Dir3::new(axis) else {
+        return;
+    };
+    let Ok(start_direction) = Dir3::new((start_point - center).reject_from(*axis)) else {
+        return;
+    };
+
+    // The pie sweeps from its local +X around its local +Y
+    *transform = Transform {
+        translation: center,
+        rotation: Quat::from_mat3(&Mat3::from_cols(
+            *start_direction,
+            *axis,
+            start_direction.cross(*axis),
+        )),
+        scale: Vec3::splat(ARC_RADIUS * gizmo_transform.compute_transform().scale.x),
+    };
+    visibility.set_if_neq(Visibility::Inherited);
+
+    if !drag_state.is_changed() {
+        return;
+    }
+    let (_, rotation) = active.result();
+    if let Some(pie_mesh) = meshes.get_mut(&mesh.0) {
+        *pie_mesh = Mesh::from(pie::Pie {
+            angle: signed_angle(rotation, *axis),
+            ..Default::default()
+        });
+    }
+    let color = LinearRgba::from(axis_color(*axis).with_alpha(0.3));
+    if materials
+        .get(&material.0)
+        .is_some_and(|pie_material| pie_material.color != color)
+    {
+        if let Some(pie_material) = materials.get_mut(&material.0) {
+            pie_material.color = color;
+        }
+    }
+}